@@ -0,0 +1,40 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Embed build metadata printed by the `version` subcommand.
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    // `rustc --version` prints "rustc 1.XX.Y (hash date)".
+    let rustc_version = command_output(&rustc, &["--version"])
+        .and_then(|output| output.split_whitespace().nth(1).map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=RMEXT_RUSTC_VERSION={}", rustc_version);
+    println!(
+        "cargo:rustc-env=RMEXT_TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+    println!(
+        "cargo:rustc-env=RMEXT_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap()
+    );
+
+    if let Some(git_describe) = command_output("git", &["describe", "--always", "--dirty"]) {
+        println!("cargo:rustc-env=RMEXT_GIT_DESCRIBE={}", git_describe);
+    }
+
+    println!("cargo:rerun-if-changed=build.rs");
+    for path in [".git/HEAD", ".git/index"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_string()).filter(|output| !output.is_empty())
+}
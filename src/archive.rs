@@ -1,2 +1,292 @@
+pub mod lha;
+pub mod rar;
 pub mod zip;
-pub mod rar;
\ No newline at end of file
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use lha::LhaFileReader;
+use rar::{RarError, RarFileReader};
+use zip::ZipFileReader;
+
+/// Archive content entry independent of the archive format.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileEntry {
+    pub file_name: String,
+    pub uncompressed_size: u64,
+    pub crc32: Option<u32>,
+    pub last_modified: Option<SystemTime>,
+    pub is_directory: bool,
+    /// Version made by of ZIP entries.
+    pub version_made_by: Option<u16>,
+    /// Whether the entry is encrypted. Only ZIP entries are detected.
+    pub is_encrypted: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Lha,
+    /// RAR5. RAR4 is also detected though it is not supported by the reader.
+    Rar,
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    /// Parse the format name. Case insensitive and `lzh` is accepted for LHA.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zip" => Ok(ArchiveFormat::Zip),
+            "lha" | "lzh" => Ok(ArchiveFormat::Lha),
+            "rar" => Ok(ArchiveFormat::Rar),
+            _ => Err(format!("unknown archive type: {} (zip, lha or rar)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Lha => "lha",
+            ArchiveFormat::Rar => "rar",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Format of the archive by its extension. Files with other extensions are
+/// ZIP if they start with a ZIP signature.
+pub fn archive_format(path: &Path) -> Option<ArchiveFormat> {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    match extension.as_str() {
+        "lha" | "lzh" => Some(ArchiveFormat::Lha),
+        "rar" => Some(ArchiveFormat::Rar),
+        extension
+            if zip::is_zip_backed_extension(extension)
+                || detect_format(path).ok().flatten() == Some(ArchiveFormat::Zip) =>
+        {
+            Some(ArchiveFormat::Zip)
+        }
+        _ => None,
+    }
+}
+
+/// Detect the archive format from the signature at the start of the file regardless of its
+/// extension. Returns `None` if the signature is not recognized.
+pub fn detect_format(path: &Path) -> io::Result<Option<ArchiveFormat>> {
+    let mut buf = Vec::with_capacity(16);
+    File::open(path)?.take(16).read_to_end(&mut buf)?;
+    Ok(detect_format_from_bytes(&buf))
+}
+
+/// Detect the archive format from the first bytes of the file.
+pub fn detect_format_from_bytes(buf: &[u8]) -> Option<ArchiveFormat> {
+    // Local file header, end of central directory record of an empty archive, or spanned archive.
+    const ZIP_SIGNATURES: [&[u8]; 3] = [b"PK\x03\x04", b"PK\x05\x06", b"PK\x07\x08"];
+    if ZIP_SIGNATURES
+        .iter()
+        .any(|signature| buf.starts_with(signature))
+    {
+        return Some(ArchiveFormat::Zip);
+    }
+    if matches!(
+        RarFileReader::check_signature(buf),
+        Ok(()) | Err(RarError::Rar4NotSupported)
+    ) {
+        return Some(ArchiveFormat::Rar);
+    }
+    // The method id of the first header, e.g.) `-lh5-`.
+    match buf.get(2..7) {
+        Some([b'-', b'l', b'h' | b'z', _, b'-']) => Some(ArchiveFormat::Lha),
+        _ => None,
+    }
+}
+
+/// Reader of archive contents independent of the archive format.
+pub trait ArchiveReader {
+    /// Read all entries of the archive.
+    fn file_entries(&mut self) -> Result<Vec<FileEntry>, ArchiveError>;
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ArchiveError {
+    /// The extension of the archive is not supported.
+    Unsupported(PathBuf),
+    Rar(RarError),
+    Io(io::Error),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Unsupported(path) => {
+                write!(f, "unsupported file type: {}", path.to_string_lossy())
+            }
+            ArchiveError::Rar(e) => write!(f, "{}", e),
+            ArchiveError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<io::Error> for ArchiveError {
+    fn from(e: io::Error) -> Self {
+        ArchiveError::Io(e)
+    }
+}
+
+impl From<RarError> for ArchiveError {
+    fn from(e: RarError) -> Self {
+        match e {
+            RarError::Io(e) => ArchiveError::Io(e),
+            e => ArchiveError::Rar(e),
+        }
+    }
+}
+
+impl From<ArchiveError> for io::Error {
+    fn from(e: ArchiveError) -> Self {
+        match e {
+            ArchiveError::Io(e) => e,
+            ArchiveError::Rar(e) => e.into(),
+            ArchiveError::Unsupported(_) => io::Error::new(ErrorKind::Unsupported, e),
+        }
+    }
+}
+
+impl ArchiveReader for ZipFileReader {
+    fn file_entries(&mut self) -> Result<Vec<FileEntry>, ArchiveError> {
+        self.seek_end_of_central_directory_record()?;
+        Ok(self
+            .entries()?
+            .map(|header| header.map(|header| FileEntry::from(&header)))
+            .collect::<io::Result<_>>()?)
+    }
+}
+
+impl ArchiveReader for LhaFileReader {
+    fn file_entries(&mut self) -> Result<Vec<FileEntry>, ArchiveError> {
+        Ok(self
+            .read_file_headers()?
+            .iter()
+            .map(FileEntry::from)
+            .collect())
+    }
+}
+
+impl ArchiveReader for RarFileReader {
+    fn file_entries(&mut self) -> Result<Vec<FileEntry>, ArchiveError> {
+        Ok(self
+            .read_file_headers()?
+            .iter()
+            .map(FileEntry::from)
+            .collect())
+    }
+}
+
+/// Open the archive with the reader for its extension, or for `override_format` regardless of
+/// the extension and the signature if given.
+/// `encoding` is used for file names which are not marked as UTF-8. RAR file names are always UTF-8.
+pub fn open_archive(
+    path: &Path,
+    encoding: &str,
+    override_format: Option<ArchiveFormat>,
+) -> Result<Box<dyn ArchiveReader>, ArchiveError> {
+    match override_format.or_else(|| archive_format(path)) {
+        Some(ArchiveFormat::Lha) => Ok(Box::new(LhaFileReader::new(path, encoding.to_string())?)),
+        Some(ArchiveFormat::Rar) => Ok(Box::new(RarFileReader::new(path)?)),
+        Some(ArchiveFormat::Zip) => Ok(Box::new(ZipFileReader::new(path, encoding.to_string())?)),
+        None => Err(ArchiveError::Unsupported(path.to_path_buf())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        archive_format, detect_format, detect_format_from_bytes, open_archive, ArchiveError,
+        ArchiveFormat,
+    };
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn open_archive_by_extension() {
+        let entries = open_archive(Path::new("resource/archive2.zip"), "utf8", None)
+            .unwrap()
+            .file_entries()
+            .unwrap();
+        assert!(!entries.is_empty());
+        assert!(matches!(
+            open_archive(Path::new("resource/archive2.7z"), "utf8", None),
+            Err(ArchiveError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn open_archive_with_override_format() {
+        let dir = std::env::temp_dir().join("rmext_open_archive_with_override_format");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.rar");
+        fs::copy("resource/archive2.zip", &path).unwrap();
+        let forced = open_archive(&path, "utf8", Some(ArchiveFormat::Zip))
+            .and_then(|mut reader| reader.file_entries());
+        let by_extension = open_archive(&path, "utf8", None).map(|_| ());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!("file.txt", forced.unwrap()[0].file_name);
+        assert!(matches!(by_extension, Err(ArchiveError::Rar(_))));
+    }
+
+    #[test]
+    fn archive_format_by_uppercase_extension() {
+        assert_eq!(Some(ArchiveFormat::Rar), archive_format(Path::new("a.RAR")));
+        assert_eq!(Some(ArchiveFormat::Lha), archive_format(Path::new("a.LZH")));
+        assert_eq!(Some(ArchiveFormat::Lha), archive_format(Path::new("a.Lha")));
+        assert_eq!(Some(ArchiveFormat::Zip), archive_format(Path::new("a.ZIP")));
+    }
+
+    #[test]
+    fn parse_archive_format() {
+        assert_eq!(Ok(ArchiveFormat::Zip), "ZIP".parse());
+        assert_eq!(Ok(ArchiveFormat::Lha), "lzh".parse());
+        assert_eq!(Ok(ArchiveFormat::Rar), "rar".parse());
+        assert!("7z".parse::<ArchiveFormat>().is_err());
+        assert_eq!("lha", ArchiveFormat::Lha.to_string());
+    }
+
+    #[test]
+    fn detect_format_by_signature() {
+        let detect = detect_format_from_bytes;
+        assert_eq!(Some(ArchiveFormat::Zip), detect(b"PK\x03\x04\x14\x00"));
+        assert_eq!(Some(ArchiveFormat::Zip), detect(b"PK\x05\x06"));
+        assert_eq!(
+            Some(ArchiveFormat::Rar),
+            detect(b"Rar!\x1a\x07\x01\x00\x33\x92")
+        );
+        assert_eq!(Some(ArchiveFormat::Rar), detect(b"Rar!\x1a\x07\x00"));
+        assert_eq!(Some(ArchiveFormat::Lha), detect(b"\x24\x00-lh5-\x10\x00"));
+        assert_eq!(Some(ArchiveFormat::Lha), detect(b"\x24\x00-lhd-\x00\x00"));
+        // Starts with `PK` but is not a zip.
+        assert_eq!(None, detect(b"PKG-INFO\n"));
+        assert_eq!(None, detect(b"PK"));
+        assert_eq!(None, detect(b"\x24\x00-lh5"));
+        assert_eq!(None, detect(b""));
+
+        assert_eq!(
+            Some(ArchiveFormat::Zip),
+            detect_format(Path::new("resource/archive.zip")).unwrap()
+        );
+        assert!(detect_format(Path::new("resource/missing.zip")).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::archive::FileEntry;
+use crate::util::time;
+
+pub struct LhaFileReader {
+    reader: BufReader<File>,
+    encoding: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LhaFileHeader {
+    pub file_name: String,
+    pub method_id: [u8; 5],
+    pub uncompressed_size: u32,
+    pub crc16: u16,
+    pub last_modified: Option<SystemTime>,
+}
+
+/// Names and total size read from the extended headers.
+struct ExtendedHeaders {
+    file_name: Option<Vec<u8>>,
+    directory_name: Option<Vec<u8>>,
+    size: u64,
+}
+
+impl ExtendedHeaders {
+    /// Build the file name from the directory name and the file name.
+    /// `base_name` is used when there is no file name header.
+    fn into_file_name(self, base_name: Vec<u8>) -> Vec<u8> {
+        let file_name = self.file_name.unwrap_or(base_name);
+        match self.directory_name {
+            Some(mut directory_name) => {
+                if !directory_name.is_empty() && !directory_name.ends_with(b"/") {
+                    directory_name.push(b'/');
+                }
+                directory_name.extend(file_name);
+                directory_name
+            }
+            None => file_name,
+        }
+    }
+}
+
+impl LhaFileReader {
+    const DIRECTORY_METHOD_ID: &'static [u8; 5] = b"-lhd-";
+    const FILE_NAME_EXTENDED_HEADER: u8 = 0x01;
+    const DIRECTORY_NAME_EXTENDED_HEADER: u8 = 0x02;
+    const DIRECTORY_NAME_SEPARATOR: u8 = 0xff;
+
+    pub fn new<P: AsRef<Path>>(path: P, encoding: String) -> Result<LhaFileReader> {
+        let file = File::open(path)?;
+        Ok(LhaFileReader {
+            reader: BufReader::new(file),
+            encoding,
+        })
+    }
+
+    /// Read all file headers.
+    /// Since LHA has no central directory, the whole archive is scanned.
+    pub fn read_file_headers(&mut self) -> Result<Vec<LhaFileHeader>> {
+        self.reader.seek(SeekFrom::Start(0))?;
+        let mut headers = Vec::new();
+        while let Some(header) = self.read_file_header()? {
+            headers.push(header);
+        }
+        Ok(headers)
+    }
+
+    /// Read a file header and skip its data. Returns `None` at the end of archive.
+    fn read_file_header(&mut self) -> Result<Option<LhaFileHeader>> {
+        let mut base = [0u8; 21];
+        let mut read = 0;
+        while read < base.len() {
+            match self.reader.read(&mut base[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        let level = base[20];
+        if read < base.len() || (base[0] == 0 && level != 2) {
+            return Ok(None);
+        }
+        if base[2] != b'-' || base[6] != b'-' {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "invalid LHA method id in file header",
+            ));
+        }
+
+        let method_id: [u8; 5] = base[2..7].try_into().unwrap();
+        let packed_size = u32::from_le_bytes(base[7..11].try_into().unwrap());
+        let uncompressed_size = u32::from_le_bytes(base[11..15].try_into().unwrap());
+        let timestamp = u32::from_le_bytes(base[15..19].try_into().unwrap());
+
+        let (file_name, crc16, last_modified) = match level {
+            0 | 1 => {
+                let header_length = i64::from(base[0]) + 2;
+                let name_length = self.read_u8()? as usize;
+                let name = self.read_bytes(name_length)?;
+                let crc16 = self.read_u16()?;
+                let last_modified =
+                    time::from_dos_date_time((timestamp >> 16) as u16, timestamp as u16);
+                let remaining = header_length - 24 - name_length as i64;
+                if level == 0 {
+                    if remaining < 0 {
+                        return Err(Self::invalid_header_size());
+                    }
+                    self.reader
+                        .seek_relative(remaining + i64::from(packed_size))?;
+                    (name, crc16, last_modified)
+                } else {
+                    // The base header ends with OS ID and the size of the first extended header.
+                    if remaining < 3 {
+                        return Err(Self::invalid_header_size());
+                    }
+                    self.reader.seek_relative(remaining - 2)?;
+                    let next_size = self.read_u16()?;
+                    let extended_headers = self.read_extended_headers(next_size)?;
+                    // The packed size of level 1 includes the extended headers.
+                    self.reader
+                        .seek_relative(i64::from(packed_size) - extended_headers.size as i64)?;
+                    (extended_headers.into_file_name(name), crc16, last_modified)
+                }
+            }
+            2 => {
+                let mut rest = [0u8; 5];
+                self.reader.read_exact(&mut rest)?;
+                let crc16 = u16::from_le_bytes([rest[0], rest[1]]);
+                let next_size = u16::from_le_bytes([rest[3], rest[4]]);
+                let extended_headers = self.read_extended_headers(next_size)?;
+                let last_modified = Some(UNIX_EPOCH + Duration::from_secs(u64::from(timestamp)));
+                self.reader.seek_relative(i64::from(packed_size))?;
+                (
+                    extended_headers.into_file_name(Vec::new()),
+                    crc16,
+                    last_modified,
+                )
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("unsupported LHA header level: {}", level),
+                ))
+            }
+        };
+
+        Ok(Some(LhaFileHeader {
+            file_name: self.decode_file_name(file_name)?,
+            method_id,
+            uncompressed_size,
+            crc16,
+            last_modified,
+        }))
+    }
+
+    /// Read extended headers.
+    fn read_extended_headers(&mut self, mut next_size: u16) -> Result<ExtendedHeaders> {
+        let mut file_name = None;
+        let mut directory_name = None;
+        let mut total_size = 0;
+        while next_size != 0 {
+            if next_size < 3 {
+                return Err(Self::invalid_header_size());
+            }
+            total_size += u64::from(next_size);
+            let header = self.read_bytes(usize::from(next_size))?;
+            let data = &header[1..header.len() - 2];
+            match header[0] {
+                Self::FILE_NAME_EXTENDED_HEADER => file_name = Some(data.to_vec()),
+                Self::DIRECTORY_NAME_EXTENDED_HEADER => {
+                    directory_name = Some(
+                        data.iter()
+                            .map(|&b| {
+                                if b == Self::DIRECTORY_NAME_SEPARATOR {
+                                    b'/'
+                                } else {
+                                    b
+                                }
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                }
+                _ => {}
+            }
+            next_size = u16::from_le_bytes([header[header.len() - 2], header[header.len() - 1]]);
+        }
+        Ok(ExtendedHeaders {
+            file_name,
+            directory_name,
+            size: total_size,
+        })
+    }
+
+    fn decode_file_name(&self, buf: Vec<u8>) -> Result<String> {
+        match self.encoding.as_ref() {
+            "utf8" => String::from_utf8(buf).map_err(|e| Error::new(ErrorKind::InvalidData, e)),
+            "cp932" => Ok(encoding_rs::SHIFT_JIS.decode(&buf).0.into_owned()),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid encoding: {}", self.encoding),
+            )),
+        }
+    }
+
+    fn invalid_header_size() -> Error {
+        Error::new(ErrorKind::InvalidData, "invalid LHA header size")
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let mut buf = [0u8; 1];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let mut buf = [0u8; 2];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; length];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn is_directory_entry(header: &LhaFileHeader) -> bool {
+        &header.method_id == Self::DIRECTORY_METHOD_ID
+    }
+}
+
+impl From<&LhaFileHeader> for FileEntry {
+    fn from(header: &LhaFileHeader) -> Self {
+        FileEntry {
+            file_name: header.file_name.clone(),
+            uncompressed_size: u64::from(header.uncompressed_size),
+            crc32: None,
+            last_modified: header.last_modified,
+            is_directory: LhaFileReader::is_directory_entry(header),
+            version_made_by: None,
+            is_encrypted: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LhaFileReader;
+    use std::fs;
+
+    fn level0_header(name: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut header = vec![(22 + name.len()) as u8, 0];
+        header.extend(b"-lh0-");
+        header.extend((data.len() as u32).to_le_bytes());
+        header.extend((data.len() as u32).to_le_bytes());
+        header.extend([0, 0, 0, 0, 0x20, 0, name.len() as u8]);
+        header.extend(name);
+        header.extend(0x1234u16.to_le_bytes());
+        header.extend(data);
+        header
+    }
+
+    fn extended_header(kind: u8, data: &[u8], next_size: u16) -> Vec<u8> {
+        let mut header = vec![kind];
+        header.extend(data);
+        header.extend(next_size.to_le_bytes());
+        header
+    }
+
+    fn level1_header(name: &[u8], directory: &[u8], data: &[u8]) -> Vec<u8> {
+        let extended = extended_header(0x02, directory, 0);
+        let mut header = vec![(25 + name.len()) as u8, 0];
+        header.extend(b"-lh5-");
+        header.extend(((extended.len() + data.len()) as u32).to_le_bytes());
+        header.extend(5u32.to_le_bytes());
+        header.extend([0, 0, 0, 0, 0x20, 1, name.len() as u8]);
+        header.extend(name);
+        header.extend(0u16.to_le_bytes());
+        header.push(b'U');
+        header.extend((extended.len() as u16).to_le_bytes());
+        header.extend(extended);
+        header.extend(data);
+        header
+    }
+
+    fn level2_header(name: &[u8], directory: &[u8], data: &[u8]) -> Vec<u8> {
+        let directory_header = extended_header(0x02, directory, 0);
+        let name_header = extended_header(0x01, name, directory_header.len() as u16);
+        let total_size = 26 + name_header.len() + directory_header.len();
+        let mut header = (total_size as u16).to_le_bytes().to_vec();
+        header.extend(b"-lh5-");
+        header.extend((data.len() as u32).to_le_bytes());
+        header.extend(7u32.to_le_bytes());
+        header.extend(1_700_000_000u32.to_le_bytes());
+        header.extend([0x20, 2]);
+        header.extend(0xbeefu16.to_le_bytes());
+        header.push(b'U');
+        header.extend((name_header.len() as u16).to_le_bytes());
+        header.extend(name_header);
+        header.extend(directory_header);
+        header.extend(data);
+        header
+    }
+
+    #[test]
+    fn read_level0_level1_and_level2_headers() {
+        let mut archive = level0_header(b"a.txt", b"abc");
+        archive.extend(level1_header(b"b.txt", b"dir\xff", b"xy"));
+        archive.extend(level2_header(
+            b"\x83e\x83L\x83X\x83g.txt",
+            b"dir\xffsub\xff",
+            b"z",
+        ));
+        archive.push(0);
+        let path = std::env::temp_dir().join("rmext_read_lha_headers.lzh");
+        fs::write(&path, archive).unwrap();
+
+        let mut reader = LhaFileReader::new(&path, "cp932".to_string()).unwrap();
+        let headers = reader.read_file_headers().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let summary = headers
+            .iter()
+            .map(|header| {
+                (
+                    header.file_name.as_str(),
+                    header.uncompressed_size,
+                    header.crc16,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("a.txt", 3, 0x1234),
+                ("dir/b.txt", 5, 0),
+                ("dir/sub/テキスト.txt", 7, 0xbeef)
+            ],
+            summary
+        );
+        assert!(headers[0].last_modified.is_none());
+        assert!(headers[2].last_modified.is_some());
+    }
+}
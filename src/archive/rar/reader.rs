@@ -1,7 +1,536 @@
-use std::fs::{File, Metadata};
-use std::io::BufReader;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Error, ErrorKind, Read, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::archive::FileEntry;
+use crate::util::time;
 
 pub struct RarFileReader {
-    metadata: Metadata,
+    path: PathBuf,
     reader: BufReader<File>,
 }
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RarFileHeader {
+    pub file_name: String,
+    pub uncompressed_size: u64,
+    pub crc32: Option<u32>,
+    pub last_modified: Option<SystemTime>,
+    pub is_directory: bool,
+    /// Win32 file attributes if the archive was created on Windows, otherwise the POSIX mode.
+    pub attributes: u32,
+}
+
+/// Header block with its type specific fields and the extra area split.
+struct HeaderBlock {
+    header_type: u64,
+    flags: u64,
+    fields: Vec<u8>,
+    extra: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum RarError {
+    Rar4NotSupported,
+    NotARar,
+    Io(io::Error),
+}
+
+impl fmt::Display for RarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RarError::Rar4NotSupported => write!(
+                f,
+                "RAR4 archives are not supported. Use unrar to list their contents instead"
+            ),
+            RarError::NotARar => write!(f, "not a RAR archive"),
+            RarError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RarError {}
+
+impl From<RarError> for Error {
+    fn from(e: RarError) -> Self {
+        match e {
+            RarError::Io(e) => e,
+            RarError::Rar4NotSupported => Error::new(ErrorKind::Unsupported, e),
+            RarError::NotARar => Error::new(ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/// Cursor over the bytes of a header block.
+struct HeaderBuf<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> HeaderBuf<'a> {
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or_else(RarFileReader::truncated_header)?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    /// Variable length integer. The lower 7 bits of each byte are data
+    /// and the highest bit means that another byte follows.
+    fn vint(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(RarFileReader::truncated_header())
+    }
+}
+
+impl RarFileReader {
+    const RAR5_SIGNATURE: [u8; 8] = [0x52, 0x61, 0x72, 0x21, 0x1a, 0x07, 0x01, 0x00];
+    const RAR4_SIGNATURE: [u8; 7] = [0x52, 0x61, 0x72, 0x21, 0x1a, 0x07, 0x00];
+    const FILE_HEADER: u64 = 2;
+    const ENCRYPTION_HEADER: u64 = 4;
+    const END_OF_ARCHIVE_HEADER: u64 = 5;
+    const HEADER_FLAG_EXTRA_AREA: u64 = 0x0001;
+    const HEADER_FLAG_DATA_AREA: u64 = 0x0002;
+    /// Data of the file continues from the previous volume.
+    const HEADER_FLAG_SPLIT_BEFORE: u64 = 0x0008;
+    const FILE_FLAG_DIRECTORY: u64 = 0x0001;
+    const FILE_FLAG_TIME: u64 = 0x0002;
+    const FILE_FLAG_CRC32: u64 = 0x0004;
+    const END_FLAG_NOT_LAST_VOLUME: u64 = 0x0001;
+    const FILE_TIME_RECORD: u64 = 0x03;
+    /// Times are Unix time in seconds instead of Windows FILETIME.
+    const FILE_TIME_FLAG_UNIX: u64 = 0x01;
+    const FILE_TIME_FLAG_MTIME: u64 = 0x02;
+
+    /// Open a RAR5 archive.
+    pub fn new<P: AsRef<Path>>(path: P) -> std::result::Result<RarFileReader, RarError> {
+        let mut reader = BufReader::new(File::open(&path).map_err(RarError::Io)?);
+        let mut signature = Vec::with_capacity(Self::RAR5_SIGNATURE.len());
+        (&mut reader)
+            .take(Self::RAR5_SIGNATURE.len() as u64)
+            .read_to_end(&mut signature)
+            .map_err(RarError::Io)?;
+        Self::check_signature(&signature)?;
+        Ok(RarFileReader {
+            path: path.as_ref().to_path_buf(),
+            reader,
+        })
+    }
+
+    /// Check that `buf` starts with the RAR5 signature.
+    pub fn check_signature(buf: &[u8]) -> std::result::Result<(), RarError> {
+        if buf.starts_with(&Self::RAR5_SIGNATURE) {
+            Ok(())
+        } else if buf.starts_with(&Self::RAR4_SIGNATURE) {
+            Err(RarError::Rar4NotSupported)
+        } else {
+            Err(RarError::NotARar)
+        }
+    }
+
+    /// Read all file headers.
+    /// Following volumes (`.part2.rar`, ...) are read until the last volume.
+    pub fn read_file_headers(&mut self) -> Result<Vec<RarFileHeader>> {
+        let mut headers = Vec::new();
+        loop {
+            let mut is_last_volume = true;
+            while let Some(block) = self.read_header()? {
+                let mut buf = HeaderBuf {
+                    data: &block.fields,
+                    position: 0,
+                };
+                match block.header_type {
+                    // A file split across volumes has a header in each volume.
+                    Self::FILE_HEADER if block.flags & Self::HEADER_FLAG_SPLIT_BEFORE == 0 => {
+                        let mut header = Self::read_file_header(&mut buf)?;
+                        if let Some(last_modified) = Self::read_mtime_record(&block.extra)? {
+                            header.last_modified = Some(last_modified);
+                        }
+                        headers.push(header);
+                    }
+                    Self::ENCRYPTION_HEADER => {
+                        return Err(Error::new(
+                            ErrorKind::Unsupported,
+                            "encrypted RAR headers are not supported",
+                        ))
+                    }
+                    Self::END_OF_ARCHIVE_HEADER => {
+                        is_last_volume = buf.vint()? & Self::END_FLAG_NOT_LAST_VOLUME == 0;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            if is_last_volume {
+                return Ok(headers);
+            }
+            let next_path = Self::next_volume_path(&self.path).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "unsupported RAR volume name: {}",
+                        self.path.to_string_lossy()
+                    ),
+                )
+            })?;
+            if !next_path.is_file() {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    format!("missing RAR volume: {}", next_path.to_string_lossy()),
+                ));
+            }
+            *self = RarFileReader::new(next_path)?;
+        }
+    }
+
+    /// Read a header block and skip its data area. Returns `None` at the end of file.
+    fn read_header(&mut self) -> Result<Option<HeaderBlock>> {
+        let mut crc32 = [0u8; 4];
+        match self.reader.read_exact(&mut crc32) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let header_size = self.read_vint()?;
+        let mut data =
+            vec![0u8; usize::try_from(header_size).map_err(|_| Self::truncated_header())?];
+        self.reader.read_exact(&mut data)?;
+
+        let mut buf = HeaderBuf {
+            data: &data,
+            position: 0,
+        };
+        let header_type = buf.vint()?;
+        let flags = buf.vint()?;
+        let extra_size = if flags & Self::HEADER_FLAG_EXTRA_AREA != 0 {
+            usize::try_from(buf.vint()?).map_err(|_| Self::truncated_header())?
+        } else {
+            0
+        };
+        if flags & Self::HEADER_FLAG_DATA_AREA != 0 {
+            let data_size = buf.vint()?;
+            self.reader
+                .seek_relative(i64::try_from(data_size).map_err(|_| Self::truncated_header())?)?;
+        }
+        // The extra area is at the end of the header.
+        let extra_start = data
+            .len()
+            .checked_sub(extra_size)
+            .filter(|&extra_start| extra_start >= buf.position)
+            .ok_or_else(Self::truncated_header)?;
+        Ok(Some(HeaderBlock {
+            header_type,
+            flags,
+            fields: data[buf.position..extra_start].to_vec(),
+            extra: data[extra_start..].to_vec(),
+        }))
+    }
+
+    /// Read the modification time in the file time record of the extra area, if any.
+    /// It is more precise than the time in the file header and may be Windows FILETIME.
+    fn read_mtime_record(extra: &[u8]) -> Result<Option<SystemTime>> {
+        let mut buf = HeaderBuf {
+            data: extra,
+            position: 0,
+        };
+        while buf.position < extra.len() {
+            let size = usize::try_from(buf.vint()?).map_err(|_| Self::truncated_header())?;
+            let mut record = HeaderBuf {
+                data: buf.bytes(size)?,
+                position: 0,
+            };
+            if record.vint()? != Self::FILE_TIME_RECORD {
+                continue;
+            }
+            let flags = record.vint()?;
+            if flags & Self::FILE_TIME_FLAG_MTIME == 0 {
+                return Ok(None);
+            }
+            return Ok(if flags & Self::FILE_TIME_FLAG_UNIX != 0 {
+                Some(UNIX_EPOCH + Duration::from_secs(u64::from(record.u32()?)))
+            } else {
+                time::from_filetime(record.u64()?)
+            });
+        }
+        Ok(None)
+    }
+
+    /// Read the fields of a file header following the common fields.
+    fn read_file_header(buf: &mut HeaderBuf) -> Result<RarFileHeader> {
+        let file_flags = buf.vint()?;
+        let uncompressed_size = buf.vint()?;
+        let attributes = buf.vint()? as u32;
+        let last_modified = if file_flags & Self::FILE_FLAG_TIME != 0 {
+            Some(UNIX_EPOCH + Duration::from_secs(u64::from(buf.u32()?)))
+        } else {
+            None
+        };
+        let crc32 = if file_flags & Self::FILE_FLAG_CRC32 != 0 {
+            Some(buf.u32()?)
+        } else {
+            None
+        };
+        // Compression information and host OS.
+        buf.vint()?;
+        buf.vint()?;
+        let name_length = buf.vint()? as usize;
+        let file_name = String::from_utf8(buf.bytes(name_length)?.to_vec())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        Ok(RarFileHeader {
+            file_name,
+            uncompressed_size,
+            crc32,
+            last_modified,
+            is_directory: file_flags & Self::FILE_FLAG_DIRECTORY != 0,
+            attributes,
+        })
+    }
+
+    fn read_vint(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let mut byte = [0u8; 1];
+            self.reader.read_exact(&mut byte)?;
+            value |= u64::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Self::truncated_header())
+    }
+
+    /// Path of the next volume keeping the width of the part number.
+    /// e.g.) `archive.part09.rar => archive.part10.rar`
+    pub fn next_volume_path(path: &Path) -> Option<PathBuf> {
+        let file_name = path.file_name()?.to_str()?;
+        let stem = file_name
+            .strip_suffix(".rar")
+            .or_else(|| file_name.strip_suffix(".RAR"))?;
+        let extension = &file_name[stem.len()..];
+        let digits_start = stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let (prefix, digits) = stem.split_at(digits_start);
+        if digits.is_empty() || !prefix.to_lowercase().ends_with(".part") {
+            return None;
+        }
+        let number = digits.parse::<u64>().ok()? + 1;
+        Some(path.with_file_name(format!(
+            "{}{:0width$}{}",
+            prefix,
+            number,
+            extension,
+            width = digits.len()
+        )))
+    }
+
+    fn truncated_header() -> Error {
+        Error::new(ErrorKind::InvalidData, "truncated RAR header")
+    }
+}
+
+impl From<&RarFileHeader> for FileEntry {
+    fn from(header: &RarFileHeader) -> Self {
+        FileEntry {
+            file_name: header.file_name.clone(),
+            uncompressed_size: header.uncompressed_size,
+            crc32: header.crc32,
+            last_modified: header.last_modified,
+            is_directory: header.is_directory,
+            version_made_by: None,
+            is_encrypted: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RarError, RarFileReader};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn vint(mut value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    fn block(header_type: u64, flags: u64, fields: &[u8], extra: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut header = vint(header_type);
+        header.extend(vint(
+            flags
+                | if extra.is_empty() { 0 } else { 0x0001 }
+                | if data.is_empty() { 0 } else { 0x0002 },
+        ));
+        if !extra.is_empty() {
+            header.extend(vint(extra.len() as u64));
+        }
+        if !data.is_empty() {
+            header.extend(vint(data.len() as u64));
+        }
+        header.extend(fields);
+        header.extend(extra);
+        let mut block = vec![0u8; 4];
+        block.extend(vint(header.len() as u64));
+        block.extend(header);
+        block.extend(data);
+        block
+    }
+
+    fn file_fields(name: &str, size: u64) -> Vec<u8> {
+        let mut fields = vint(0x0004);
+        fields.extend(vint(size));
+        fields.extend(vint(0x20));
+        fields.extend(0x1234_5678u32.to_le_bytes());
+        fields.extend(vint(0));
+        fields.extend(vint(1));
+        fields.extend(vint(name.len() as u64));
+        fields.extend(name.as_bytes());
+        fields
+    }
+
+    fn file_block(name: &str, size: u64, flags: u64, data: &[u8]) -> Vec<u8> {
+        block(2, flags, &file_fields(name, size), &[], data)
+    }
+
+    fn volume(files: &[Vec<u8>], is_last: bool) -> Vec<u8> {
+        let mut archive = RarFileReader::RAR5_SIGNATURE.to_vec();
+        archive.extend(block(1, 0, &vint(0x0001), &[], &[]));
+        for file in files {
+            archive.extend(file);
+        }
+        archive.extend(block(5, 0, &vint(if is_last { 0 } else { 1 }), &[], &[]));
+        archive
+    }
+
+    #[test]
+    fn read_multi_volume_file_headers() {
+        let dir = std::env::temp_dir().join("rmext_read_multi_volume_file_headers");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("archive.part1.rar"),
+            volume(
+                &[
+                    file_block("a.txt", 3, 0, b"abc"),
+                    file_block("dir/b.txt", 4, 0x0010, b"de"),
+                ],
+                false,
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("archive.part2.rar"),
+            volume(
+                &[
+                    file_block("dir/b.txt", 4, 0x0008, b"fg"),
+                    file_block("c.txt", 1, 0, b"h"),
+                ],
+                true,
+            ),
+        )
+        .unwrap();
+
+        let headers = RarFileReader::new(dir.join("archive.part1.rar"))
+            .unwrap()
+            .read_file_headers()
+            .unwrap();
+        fs::remove_file(dir.join("archive.part2.rar")).unwrap();
+        let missing = RarFileReader::new(dir.join("archive.part1.rar"))
+            .unwrap()
+            .read_file_headers()
+            .unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let summary = headers
+            .iter()
+            .map(|header| (header.file_name.as_str(), header.uncompressed_size))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![("a.txt", 3), ("dir/b.txt", 4), ("c.txt", 1)], summary);
+        assert_eq!(Some(0x1234_5678), headers[0].crc32);
+        assert!(missing.to_string().contains("archive.part2.rar"));
+    }
+
+    #[test]
+    fn read_attributes_and_filetime_mtime() {
+        // 2023-11-14 22:13:20.5 UTC as FILETIME.
+        let filetime = (1_700_000_000 + 11_644_473_600) * 10_000_000 + 5_000_000u64;
+        let mut record = vint(0x03);
+        record.extend(vint(0x02));
+        record.extend(filetime.to_le_bytes());
+        let mut extra = vint(record.len() as u64);
+        extra.extend(record);
+        let dir = std::env::temp_dir().join("rmext_read_attributes_and_filetime_mtime");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.rar");
+        fs::write(
+            &path,
+            volume(&[block(2, 0, &file_fields("a.txt", 1), &extra, b"a")], true),
+        )
+        .unwrap();
+
+        let headers = RarFileReader::new(&path)
+            .unwrap()
+            .read_file_headers()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(0x20, headers[0].attributes);
+        let mtime = headers[0]
+            .last_modified
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap();
+        assert!(mtime.abs_diff(Duration::from_secs(1_700_000_000)) < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn check_rar_signature() {
+        assert!(RarFileReader::check_signature(b"Rar!\x1a\x07\x01\x00\x33").is_ok());
+        assert!(matches!(
+            RarFileReader::check_signature(b"Rar!\x1a\x07\x00\xcf"),
+            Err(RarError::Rar4NotSupported)
+        ));
+        assert!(matches!(
+            RarFileReader::check_signature(b"PK\x03\x04"),
+            Err(RarError::NotARar)
+        ));
+    }
+
+    #[test]
+    fn increment_part_number() {
+        assert_eq!(
+            Some(PathBuf::from("dir/a.part2.rar")),
+            RarFileReader::next_volume_path(Path::new("dir/a.part1.rar"))
+        );
+        assert_eq!(
+            Some(PathBuf::from("a.part10.rar")),
+            RarFileReader::next_volume_path(Path::new("a.part09.rar"))
+        );
+        assert_eq!(None, RarFileReader::next_volume_path(Path::new("a.rar")));
+    }
+}
@@ -1 +1,35 @@
-pub mod reader;
\ No newline at end of file
+pub mod reader;
+pub mod validate;
+
+pub use reader::{
+    archive_origin_os, decode_version_made_by, deduplicate_by_name, detect_and_strip_top_dir,
+    find_by_file_name, is_symlink_entry, sort_by_file_name, CentralDirectoryFileHeader,
+    CentralDirectoryIter, CompressionMethod, EndOfCentralDirectory, EocdDump, LocalFileHeader,
+    UniqueByName, ZipFileReader,
+};
+pub use validate::{validate_zip_consistency, ValidationReport};
+
+/// Extensions of ZIP archives and of formats based on ZIP such as Office Open XML and ODF.
+pub const ZIP_BACKED_EXTENSIONS: &[&str] = &[
+    "zip", "docx", "xlsx", "pptx", "odt", "ods", "odp", "epub", "apk", "jar",
+];
+
+/// Whether files with the extension are read as ZIP archives. Case insensitive.
+pub fn is_zip_backed_extension(ext: &str) -> bool {
+    ZIP_BACKED_EXTENSIONS
+        .iter()
+        .any(|zip_backed| zip_backed.eq_ignore_ascii_case(ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_zip_backed_extension;
+
+    #[test]
+    fn zip_backed_extensions() {
+        assert!(is_zip_backed_extension("zip"));
+        assert!(is_zip_backed_extension("DOCX"));
+        assert!(is_zip_backed_extension("epub"));
+        assert!(!is_zip_backed_extension("lzh"));
+    }
+}
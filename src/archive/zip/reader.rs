@@ -1,39 +1,267 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::{File, Metadata};
-use std::io::{BufReader, Read, Result, Seek, SeekFrom};
-use std::path::Path;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use std::vec;
 
+use crate::archive::FileEntry;
+use crate::util::time;
+
 pub struct ZipFileReader {
+    path: PathBuf,
     metadata: Metadata,
     reader: BufReader<File>,
     encoding: String,
+    /// Encoding used even for file names marked as UTF-8.
+    force_encoding: Option<String>,
+    /// Encoding of the archive comment. `encoding` is used if `None`.
+    comment_encoding: Option<String>,
+    end_of_central_directory: Option<EndOfCentralDirectory>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LocalFileHeader {
+    pub file_name: String,
+    pub extra_field_length: u16,
+    /// Bit 3 of general purpose bit flag. CRC-32 and sizes follow the file data.
+    pub data_descriptor_used: bool,
+}
+
+/// Fields of the end of central directory record (EOCD).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EndOfCentralDirectory {
+    pub number_of_this_disk: u16,
+    pub disk_where_central_directory_starts: u16,
+    pub number_of_central_directory_records_on_this_disk: u16,
+    pub total_number_of_central_directory_records: u16,
+    pub size_of_central_directory: u32,
+    pub offset_of_start_of_central_directory: u32,
+    pub comment: String,
+}
+
+impl EndOfCentralDirectory {
+    /// Fields are set to the maximum value when the values are in the ZIP64 record.
+    pub fn is_zip64(&self) -> bool {
+        self.number_of_this_disk == u16::MAX
+            || self.disk_where_central_directory_starts == u16::MAX
+            || self.number_of_central_directory_records_on_this_disk == u16::MAX
+            || self.total_number_of_central_directory_records == u16::MAX
+            || self.size_of_central_directory == u32::MAX
+            || self.offset_of_start_of_central_directory == u32::MAX
+    }
 }
 
+/// Raw bytes and parsed fields of the EOCD for debugging.
 #[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EocdDump {
+    pub offset: u64,
+    /// EOCD including the comment.
+    pub bytes: Vec<u8>,
+    pub end_of_central_directory: EndOfCentralDirectory,
+    pub zip64_locator: Option<Vec<u8>>,
+    pub zip64_end_of_central_directory: Option<Vec<u8>>,
+}
+
+impl fmt::Display for EocdDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |bytes: &[u8]| {
+            bytes
+                .chunks(16)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>()
+                .join("\n\t")
+        };
+        let eocd = &self.end_of_central_directory;
+        writeln!(f, "EOCD at {}:\n\t{}", self.offset, hex(&self.bytes))?;
+        writeln!(f, "Number of this disk: {}", eocd.number_of_this_disk)?;
+        writeln!(
+            f,
+            "Disk where central directory starts: {}",
+            eocd.disk_where_central_directory_starts
+        )?;
+        writeln!(
+            f,
+            "Number of central directory records on this disk: {}",
+            eocd.number_of_central_directory_records_on_this_disk
+        )?;
+        writeln!(
+            f,
+            "Total number of central directory records: {}",
+            eocd.total_number_of_central_directory_records
+        )?;
+        writeln!(
+            f,
+            "Size of central directory: {}",
+            eocd.size_of_central_directory
+        )?;
+        writeln!(
+            f,
+            "Offset of start of central directory: {}",
+            eocd.offset_of_start_of_central_directory
+        )?;
+        writeln!(f, "Comment length: {}", self.bytes.len() - 22)?;
+        write!(f, "Comment: {:?}", eocd.comment)?;
+        if let Some(zip64_locator) = &self.zip64_locator {
+            write!(f, "\nZIP64 EOCD locator:\n\t{}", hex(zip64_locator))?;
+        }
+        if let Some(zip64_end_of_central_directory) = &self.zip64_end_of_central_directory {
+            write!(
+                f,
+                "\nZIP64 EOCD:\n\t{}",
+                hex(zip64_end_of_central_directory)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for EndOfCentralDirectory {
+    /// e.g.) `Entries: 342 | CD Offset: 18291234 | Comment: "Released 2024-01"`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Entries: {} | CD Offset: {}",
+            self.total_number_of_central_directory_records,
+            self.offset_of_start_of_central_directory
+        )?;
+        if self.number_of_this_disk != 0 {
+            write!(f, " | Disks: {}", u32::from(self.number_of_this_disk) + 1)?;
+        }
+        if self.is_zip64() {
+            write!(f, " | ZIP64")?;
+        }
+        write!(f, " | Comment: {:?}", self.comment)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct CentralDirectoryFileHeader {
     pub file_name: String,
     pub uncompressed_size: u32,
+    pub crc32: Option<u32>,
+    pub compression_method: Option<CompressionMethod>,
     pub general_purpose_bit_flag: [u8; 2],
+    /// NTFS timestamp if present, otherwise MS-DOS timestamp.
+    pub last_modified: Option<SystemTime>,
+    /// Encoding used to decode the file name.
+    pub file_name_encoding: &'static str,
+    pub relative_offset_of_local_header: u32,
+    /// OS in the upper byte and ZIP specification version in the lower byte.
+    pub version_made_by: u16,
+    /// Unix file mode in the upper 16 bits if created on Unix, MS-DOS attributes in the lower byte.
+    pub external_file_attributes: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionMethod {
+    Stored,
+    Deflate,
+    Deflate64,
+    Bzip2,
+    Lzma,
+    Zstandard,
+    Xz,
+    Aes,
+    Other(u16),
+}
+
+impl From<u16> for CompressionMethod {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => CompressionMethod::Stored,
+            8 => CompressionMethod::Deflate,
+            9 => CompressionMethod::Deflate64,
+            12 => CompressionMethod::Bzip2,
+            14 => CompressionMethod::Lzma,
+            93 => CompressionMethod::Zstandard,
+            95 => CompressionMethod::Xz,
+            99 => CompressionMethod::Aes,
+            v => CompressionMethod::Other(v),
+        }
+    }
+}
+
+impl fmt::Display for CompressionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompressionMethod::Other(v) => write!(f, "Unknown({})", v),
+            method => write!(f, "{:?}", method),
+        }
+    }
+}
+
+impl fmt::Display for CentralDirectoryFileHeader {
+    /// e.g.) `src/main.rs (4321 bytes, crc32: deadbeef, method: Deflate)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} bytes", self.file_name, self.uncompressed_size)?;
+        if let Some(crc32) = self.crc32 {
+            write!(f, ", crc32: {:08x}", crc32)?;
+        }
+        if let Some(compression_method) = self.compression_method {
+            write!(f, ", method: {}", compression_method)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Lazy iterator over the central directory file headers.
+pub struct CentralDirectoryIter<'a> {
+    zip: &'a mut ZipFileReader,
+    position: u64,
+    remaining: usize,
 }
 
 impl ZipFileReader {
     const END_OF_CENTRAL_DIR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
     const CENTRAL_DIRECTORY_ENTRY_SIGNATURE: [u8; 4] = [0x50, 0x4B, 0x01, 0x02];
+    const LOCAL_FILE_HEADER_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x03, 0x04];
     const MAX_COMMENT_LENGTH: u64 = 65535;
+    const NTFS_EXTRA_FIELD_ID: u16 = 0x000a;
+    const ZIP64_END_OF_CENTRAL_DIR_LOCATOR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x07];
+    const ZIP64_END_OF_CENTRAL_DIR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x06];
 
-    pub fn new<P: AsRef<Path>>(path: P, encoding: String) -> ZipFileReader {
-        let file = match File::open(&path) {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("Can not open {:?}. Because {}", path.as_ref(), e);
-                std::process::exit(1);
-            }
-        };
-        ZipFileReader {
-            metadata: file.metadata().unwrap(),
+    pub fn new<P: AsRef<Path>>(path: P, encoding: String) -> Result<ZipFileReader> {
+        let file = File::open(&path)?;
+        Ok(ZipFileReader {
+            path: path.as_ref().to_path_buf(),
+            metadata: file.metadata()?,
             reader: BufReader::new(file),
-            encoding: encoding,
-        }
+            encoding,
+            force_encoding: None,
+            comment_encoding: None,
+            end_of_central_directory: None,
+        })
+    }
+
+    /// Decode all file names with `force_encoding` ignoring the UTF-8 flag (bit 11).
+    /// A workaround for archivers which set the flag on file names in a legacy encoding.
+    pub fn set_force_encoding(&mut self, force_encoding: Option<String>) {
+        self.force_encoding = force_encoding;
+    }
+
+    /// Decode the archive comment with `comment_encoding` instead of the encoding of file names.
+    pub fn set_comment_encoding(&mut self, comment_encoding: Option<String>) {
+        self.comment_encoding = comment_encoding;
+    }
+
+    /// Read the archive comment in the end of central directory record.
+    pub fn read_archive_comment(&mut self) -> Result<String> {
+        self.seek_end_of_central_directory_record()?;
+        Ok(self
+            .end_of_central_directory
+            .as_ref()
+            .map(|end_of_central_directory| end_of_central_directory.comment.clone())
+            .unwrap_or_default())
     }
 
     pub fn seek_end_of_central_directory_record(&mut self) -> Result<()> {
@@ -44,11 +272,11 @@ impl ZipFileReader {
         self.reader.seek_relative(-4)?;
         let mut comment_length = 0;
         let mut stream_pos = 22;
-        while (comment_length + self.reader.stream_position()?) != file_size {
+        while (comment_length + self.stream_position()?) != file_size {
             let mut buf = [0u8; 4];
             while buf != Self::END_OF_CENTRAL_DIR_SIGNATURE {
                 stream_pos -= 4;
-                self.reader.read(&mut buf)?;
+                self.reader.read_exact(&mut buf)?;
 
                 let mut offset = 0;
                 for (i, val) in buf.iter().rev().enumerate() {
@@ -60,8 +288,10 @@ impl ZipFileReader {
                 stream_pos += offset as u64;
 
                 if file_size < stream_pos || Self::MAX_COMMENT_LENGTH < stream_pos {
-                    eprintln!("End of central directory record (EOCD) not found.");
-                    std::process::exit(1);
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "End of central directory record (EOCD) not found.",
+                    ));
                 }
 
                 self.reader.seek_relative(-i64::try_from(offset).unwrap())?;
@@ -71,124 +301,1027 @@ impl ZipFileReader {
 
             comment_length = {
                 let mut buf = [0u8; 2];
-                self.reader.read(&mut buf)?;
+                self.reader.read_exact(&mut buf)?;
                 u64::from(u16::from_le_bytes(buf))
             };
         }
         self.reader.seek_relative(-22)?;
+        self.end_of_central_directory = Some(self.read_end_of_central_directory_record()?);
         Ok(())
     }
 
-    pub fn read_central_directory_file_header(&mut self) -> Vec<CentralDirectoryFileHeader> {
-        self.reader.seek_relative(10).unwrap();
+    /// Fields of the EOCD read by `seek_end_of_central_directory_record`.
+    pub fn end_of_central_directory(&self) -> Option<&EndOfCentralDirectory> {
+        self.end_of_central_directory.as_ref()
+    }
+
+    /// Dump the EOCD, and the ZIP64 EOCD locator and the ZIP64 EOCD if present.
+    pub fn dump_eocd(&mut self) -> Result<EocdDump> {
+        self.seek_end_of_central_directory_record()?;
+        let offset = self.stream_position()?;
+        let end_of_central_directory = self.end_of_central_directory.clone().unwrap_or_default();
+        // The comment continues to the end of file.
+        let bytes = self.read_bytes_at(offset, (self.file_size() - offset) as usize)?;
+
+        let mut zip64_locator = None;
+        let mut zip64_end_of_central_directory = None;
+        if offset >= 20 {
+            let locator = self.read_bytes_at(offset - 20, 20)?;
+            if locator[0..4] == Self::ZIP64_END_OF_CENTRAL_DIR_LOCATOR_SIGNATURE {
+                let zip64_offset = u64::from_le_bytes(locator[8..16].try_into().unwrap());
+                let mut record = self.read_bytes_at(zip64_offset, 12)?;
+                if record[0..4] == Self::ZIP64_END_OF_CENTRAL_DIR_SIGNATURE {
+                    let size = u64::from_le_bytes(record[4..12].try_into().unwrap());
+                    record.extend(self.read_bytes_at(zip64_offset + 12, size as usize)?);
+                    zip64_end_of_central_directory = Some(record);
+                }
+                zip64_locator = Some(locator);
+            }
+        }
+        Ok(EocdDump {
+            offset,
+            bytes,
+            end_of_central_directory,
+            zip64_locator,
+            zip64_end_of_central_directory,
+        })
+    }
+
+    /// Read the EOCD at the current position and seek back to it.
+    fn read_end_of_central_directory_record(&mut self) -> Result<EndOfCentralDirectory> {
+        let mut buf = [0u8; 22];
+        self.reader.read_exact(&mut buf)?;
+        let le_u16 = |i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+        let le_u32 = |i: usize| u32::from_le_bytes(buf[i..i + 4].try_into().unwrap());
+        let comment_length = le_u16(20);
+        let comment = {
+            let mut buf = vec![0u8; usize::from(comment_length)];
+            self.reader.read_exact(&mut buf)?;
+            match self
+                .comment_encoding
+                .as_ref()
+                .unwrap_or(&self.encoding)
+                .as_ref()
+            {
+                "cp932" => encoding_rs::SHIFT_JIS.decode(&buf).0.into_owned(),
+                _ => String::from_utf8_lossy(&buf).into_owned(),
+            }
+        };
+        self.reader
+            .seek_relative(-(22 + i64::from(comment_length)))?;
+        Ok(EndOfCentralDirectory {
+            number_of_this_disk: le_u16(4),
+            disk_where_central_directory_starts: le_u16(6),
+            number_of_central_directory_records_on_this_disk: le_u16(8),
+            total_number_of_central_directory_records: le_u16(10),
+            size_of_central_directory: le_u32(12),
+            offset_of_start_of_central_directory: le_u32(16),
+            comment,
+        })
+    }
+
+    /// Guess the encoding of file names from the first `n` entries.
+    /// Returns `None` if the entries decode with any encoding, e.g. all of them are ASCII.
+    pub fn probe_encoding<P: AsRef<Path>>(path: P, n: usize) -> Result<Option<&'static str>> {
+        let mut reader = ZipFileReader::new(path, "utf8".to_string())?;
+        reader.seek_end_of_central_directory_record()?;
+        let mut utf8 = false;
+        for header in reader.entries()?.take(n) {
+            match header {
+                Ok(header) if header.file_name_encoding == "cp932" => return Ok(Some("cp932")),
+                Ok(header) => {
+                    utf8 |= !Self::is_utf8(header.general_purpose_bit_flag)
+                        && !header.file_name.is_ascii()
+                }
+                Err(_) => return Ok(Some("cp932")),
+            }
+        }
+        Ok(utf8.then_some("utf8"))
+    }
+
+    /// Open the archive again and seek to the same position.
+    pub fn try_clone(&self) -> Result<ZipFileReader> {
+        let mut file = self.reader.get_ref();
+        // Bytes already buffered are not consumed yet.
+        let position = file.stream_position()? - self.reader.buffer().len() as u64;
+        let mut clone = ZipFileReader::new(&self.path, self.encoding.clone())?;
+        clone.force_encoding = self.force_encoding.clone();
+        clone.comment_encoding = self.comment_encoding.clone();
+        clone.reader.seek(SeekFrom::Start(position))?;
+        Ok(clone)
+    }
+
+    /// Size of the archive file in bytes.
+    pub(crate) fn file_size(&self) -> u64 {
+        self.metadata.len()
+    }
+
+    /// Read `length` bytes at `offset`.
+    pub(crate) fn read_bytes_at(&mut self, offset: u64, length: usize) -> Result<Vec<u8>> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0u8; length];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Current position of the underlying reader.
+    pub(crate) fn stream_position(&mut self) -> Result<u64> {
+        self.reader.stream_position()
+    }
+
+    /// Iterate central directory file headers.
+    /// The reader must be positioned at the end of central directory record.
+    pub fn entries(&mut self) -> Result<CentralDirectoryIter<'_>> {
+        self.reader.seek_relative(10)?;
 
         let total_number_of_central_directory_records = {
             let mut buf = [0u8; 2];
-            self.reader.read(&mut buf).unwrap();
+            self.reader.read_exact(&mut buf)?;
             u16::from_le_bytes(buf) as usize
         };
 
-        let mut central_directory_reocrds: Vec<CentralDirectoryFileHeader> =
-            Vec::with_capacity(total_number_of_central_directory_records);
-
-        self.reader.seek_relative(4).unwrap();
+        self.reader.seek_relative(4)?;
 
         let offset_of_start_of_central_directory = {
             let mut buf = [0u8; 4];
-            self.reader.read(&mut buf).unwrap();
+            self.reader.read_exact(&mut buf)?;
             u64::from(u32::from_le_bytes(buf))
         };
 
-        self.reader
-            .seek(SeekFrom::Start(offset_of_start_of_central_directory))
-            .unwrap();
+        Ok(CentralDirectoryIter {
+            zip: self,
+            position: offset_of_start_of_central_directory,
+            remaining: total_number_of_central_directory_records,
+        })
+    }
 
-        let mut comment_length = 0;
-        for _n in 0..total_number_of_central_directory_records {
-            self.reader.seek_relative(comment_length).unwrap();
+    pub fn read_central_directory_file_header(
+        &mut self,
+    ) -> Result<Vec<CentralDirectoryFileHeader>> {
+        self.entries()?.collect::<Result<Vec<_>>>()
+    }
+
+    /// Read a central directory file header at `offset`.
+    /// Returns the header and the offset of the next header.
+    /// The header is an error if the file name can not be decoded.
+    fn read_central_directory_file_header_at(
+        &mut self,
+        offset: u64,
+    ) -> Result<(Result<CentralDirectoryFileHeader>, u64)> {
+        self.reader.seek(SeekFrom::Start(offset))?;
 
+        let mut buf = [0u8; 4];
+        self.reader.read_exact(&mut buf)?;
+
+        if buf != Self::CENTRAL_DIRECTORY_ENTRY_SIGNATURE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "invalid central directory file header signature at {}",
+                    offset
+                ),
+            ));
+        }
+
+        let version_made_by = {
+            let mut buf = [0u8; 2];
+            self.reader.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf)
+        };
+
+        self.reader.seek_relative(2)?;
+
+        let general_purpose_bit_flag = {
+            let mut buf = [0u8; 2];
+            self.reader.read_exact(&mut buf)?;
+            buf
+        };
+
+        let compression_method = {
+            let mut buf = [0u8; 2];
+            self.reader.read_exact(&mut buf)?;
+            CompressionMethod::from(u16::from_le_bytes(buf))
+        };
+
+        let last_mod_file_time = {
+            let mut buf = [0u8; 2];
+            self.reader.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf)
+        };
+
+        let last_mod_file_date = {
+            let mut buf = [0u8; 2];
+            self.reader.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf)
+        };
+
+        let crc32 = {
             let mut buf = [0u8; 4];
-            self.reader.read(&mut buf).unwrap();
+            self.reader.read_exact(&mut buf)?;
+            u32::from_le_bytes(buf)
+        };
 
-            assert_eq!(buf, Self::CENTRAL_DIRECTORY_ENTRY_SIGNATURE);
+        self.reader.seek_relative(4)?;
 
-            self.reader.seek_relative(4).unwrap();
+        let uncompressed_size = {
+            let mut buf = [0u8; 4];
+            self.reader.read_exact(&mut buf)?;
+            u32::from_le_bytes(buf)
+        };
 
-            let general_purpose_bit_flag = {
-                let mut buf = [0u8; 2];
-                self.reader.read(&mut buf).unwrap();
-                buf
-            };
+        let file_name_length = {
+            let mut buf = [0u8; 2];
+            self.reader.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf) as usize
+        };
 
-            self.reader.seek_relative(14).unwrap();
+        let extra_field_length = {
+            let mut buf = [0u8; 2];
+            self.reader.read_exact(&mut buf)?;
+            u16::from_le_bytes(buf) as usize
+        };
 
-            let uncompressed_size = {
-                let mut buf = [0u8; 4];
-                self.reader.read(&mut buf).unwrap();
-                u32::from_le_bytes(buf)
-            };
+        let comment_length = {
+            let mut buf = [0u8; 2];
+            self.reader.read_exact(&mut buf)?;
+            u64::from(u16::from_le_bytes(buf))
+        };
 
-            let file_name_length = {
-                let mut buf = [0u8; 2];
-                self.reader.read(&mut buf).unwrap();
-                u16::from_le_bytes(buf) as usize
-            };
+        // Disk number start and internal file attributes.
+        self.reader.seek_relative(4)?;
 
-            let extra_field_length = {
-                let mut buf = [0u8; 2];
-                self.reader.read(&mut buf).unwrap();
-                i64::from(u16::from_le_bytes(buf))
-            };
+        let external_file_attributes = {
+            let mut buf = [0u8; 4];
+            self.reader.read_exact(&mut buf)?;
+            u32::from_le_bytes(buf)
+        };
 
-            comment_length = {
-                let mut buf = [0u8; 2];
-                self.reader.read(&mut buf).unwrap();
-                i64::from(u16::from_le_bytes(buf))
-            };
+        let relative_offset_of_local_header = {
+            let mut buf = [0u8; 4];
+            self.reader.read_exact(&mut buf)?;
+            u32::from_le_bytes(buf)
+        };
 
-            self.reader.seek_relative(12).unwrap();
-
-            let file_name = {
-                let mut buf = vec![0u8; file_name_length];
-                self.reader.read_exact(&mut buf).unwrap();
-                if Self::is_utf8(general_purpose_bit_flag) {
-                    String::from_utf8(buf).unwrap()
-                } else {
-                    match self.encoding.as_ref() {
-                        "utf8" => match String::from_utf8(buf.clone()) {
-                            Ok(v) => v,
-                            Err(e) => {
-                                if cfg!(windows) {
-                                    // TODO Consider locale.
-                                    encoding_rs::SHIFT_JIS.decode(&buf).0.into_owned()
-                                } else {
-                                    eprintln!("{}", e);
-                                    std::process::exit(1);
-                                }
-                            }
-                        },
-                        "cp932" => encoding_rs::SHIFT_JIS.decode(&buf).0.into_owned(),
-                        _ => {
-                            eprintln!("invalid encoding: {}", self.encoding);
-                            std::process::exit(1);
-                        }
-                    }
-                }
-            };
+        let file_name = {
+            let mut buf = vec![0u8; file_name_length];
+            self.reader.read_exact(&mut buf)?;
+            self.decode_file_name(
+                buf,
+                general_purpose_bit_flag,
+                self.force_encoding.as_deref(),
+            )
+        };
+
+        let extra_field = {
+            let mut buf = vec![0u8; extra_field_length];
+            self.reader.read_exact(&mut buf)?;
+            buf
+        };
 
-            self.reader.seek_relative(extra_field_length).unwrap();
+        let last_modified = Self::read_ntfs_last_modified(&extra_field)
+            .or_else(|| time::from_dos_date_time(last_mod_file_date, last_mod_file_time));
 
-            central_directory_reocrds.push(CentralDirectoryFileHeader {
+        let next_offset =
+            offset + 46 + file_name_length as u64 + extra_field_length as u64 + comment_length;
+
+        let header = file_name.map(
+            |(file_name, file_name_encoding)| CentralDirectoryFileHeader {
                 file_name,
                 uncompressed_size,
+                crc32: Some(crc32),
+                compression_method: Some(compression_method),
                 general_purpose_bit_flag,
-            })
+                last_modified,
+                file_name_encoding,
+                relative_offset_of_local_header,
+                version_made_by,
+                external_file_attributes,
+            },
+        );
+        Ok((header, next_offset))
+    }
+
+    /// Read a local file header at `offset`.
+    pub fn read_local_file_header_at(&mut self, offset: u64) -> Result<LocalFileHeader> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        let mut buf = [0u8; 30];
+        self.reader.read_exact(&mut buf)?;
+
+        if buf[0..4] != Self::LOCAL_FILE_HEADER_SIGNATURE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid local file header signature at {}", offset),
+            ));
         }
 
-        central_directory_reocrds
+        let general_purpose_bit_flag = [buf[6], buf[7]];
+        let file_name_length = u16::from_le_bytes([buf[26], buf[27]]) as usize;
+        let extra_field_length = u16::from_le_bytes([buf[28], buf[29]]);
+
+        let file_name = {
+            let mut buf = vec![0u8; file_name_length];
+            self.reader.read_exact(&mut buf)?;
+            self.decode_file_name(
+                buf,
+                general_purpose_bit_flag,
+                self.force_encoding.as_deref(),
+            )?
+            .0
+        };
+
+        Ok(LocalFileHeader {
+            file_name,
+            extra_field_length,
+            data_descriptor_used: (u16::from_le_bytes(general_purpose_bit_flag) >> 3) & 1 == 1,
+        })
+    }
+
+    /// Decode file name. Returns the file name and the encoding used.
+    /// The UTF-8 flag is not checked if `force_encoding` is given.
+    fn decode_file_name(
+        &self,
+        buf: Vec<u8>,
+        general_purpose_bit_flag: [u8; 2],
+        force_encoding: Option<&str>,
+    ) -> Result<(String, &'static str)> {
+        match force_encoding {
+            Some("utf8") => {
+                return String::from_utf8(buf)
+                    .map(|v| (v, "utf8"))
+                    .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+            }
+            Some("cp932") => {
+                return Ok((encoding_rs::SHIFT_JIS.decode(&buf).0.into_owned(), "cp932"))
+            }
+            Some(force_encoding) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid encoding: {}", force_encoding),
+                ))
+            }
+            None => {}
+        }
+        if Self::is_utf8(general_purpose_bit_flag) {
+            return String::from_utf8(buf)
+                .map(|v| (v, "utf8"))
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e));
+        }
+        match self.encoding.as_ref() {
+            "utf8" => match String::from_utf8(buf) {
+                Ok(v) => Ok((v, "utf8")),
+                Err(e) => {
+                    if cfg!(windows) {
+                        // TODO Consider locale.
+                        Ok((
+                            encoding_rs::SHIFT_JIS.decode(e.as_bytes()).0.into_owned(),
+                            "cp932",
+                        ))
+                    } else {
+                        Err(Error::new(ErrorKind::InvalidData, e))
+                    }
+                }
+            },
+            "cp932" => Ok((encoding_rs::SHIFT_JIS.decode(&buf).0.into_owned(), "cp932")),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid encoding: {}", self.encoding),
+            )),
+        }
     }
 
-    fn is_utf8(general_purpose_bit_flag: [u8; 2]) -> bool {
-        (general_purpose_bit_flag[0] >> 5) & 1 == 1
+    /// Read the last modification time from the NTFS extra field.
+    fn read_ntfs_last_modified(extra_field: &[u8]) -> Option<SystemTime> {
+        let le_u16 =
+            |buf: &[u8], i: usize| Some(u16::from_le_bytes(buf.get(i..i + 2)?.try_into().ok()?));
+        let mut i = 0;
+        while i + 4 <= extra_field.len() {
+            let id = le_u16(extra_field, i)?;
+            let size = le_u16(extra_field, i + 2)? as usize;
+            let data = extra_field.get(i + 4..i + 4 + size)?;
+            if id == Self::NTFS_EXTRA_FIELD_ID {
+                // Skip reserved 4 bytes, then attribute tags follow.
+                let mut j = 4;
+                while j + 4 <= data.len() {
+                    let tag = le_u16(data, j)?;
+                    let tag_size = le_u16(data, j + 2)? as usize;
+                    if tag == 0x0001 && tag_size >= 8 {
+                        let mtime = u64::from_le_bytes(data.get(j + 4..j + 12)?.try_into().ok()?);
+                        return time::from_filetime(mtime);
+                    }
+                    j += 4 + tag_size;
+                }
+            }
+            i += 4 + size;
+        }
+        None
+    }
+
+    /// Directory entries end with `/` and have no content.
+    pub fn is_directory_entry(header: &CentralDirectoryFileHeader) -> bool {
+        header.file_name.ends_with('/') && header.uncompressed_size == 0
+    }
+
+    /// Bit 0 of general purpose bit flag. The file data is encrypted.
+    pub fn is_encrypted(general_purpose_bit_flag: [u8; 2]) -> bool {
+        u16::from_le_bytes(general_purpose_bit_flag) & 1 == 1
+    }
+
+    /// Bit 11 of general purpose bit flag (language encoding flag).
+    pub fn is_utf8(general_purpose_bit_flag: [u8; 2]) -> bool {
+        (u16::from_le_bytes(general_purpose_bit_flag) >> 11) & 1 == 1
+    }
+}
+
+/// Header compared, hashed and ordered by the file name only.
+#[derive(Clone, Debug)]
+pub struct UniqueByName(pub CentralDirectoryFileHeader);
+
+impl PartialEq for UniqueByName {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.file_name == other.0.file_name
+    }
+}
+
+impl Eq for UniqueByName {}
+
+impl Hash for UniqueByName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.file_name.hash(state);
+    }
+}
+
+impl Ord for UniqueByName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.file_name.cmp(&other.0.file_name)
+    }
+}
+
+impl PartialOrd for UniqueByName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Sort headers by file name so that they can be searched with `find_by_file_name`.
+pub fn sort_by_file_name(headers: &mut [CentralDirectoryFileHeader]) {
+    headers.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+}
+
+/// Find the header named `file_name` in headers sorted by `sort_by_file_name`.
+pub fn find_by_file_name<'a>(
+    headers: &'a [CentralDirectoryFileHeader],
+    file_name: &str,
+) -> Option<&'a CentralDirectoryFileHeader> {
+    headers
+        .binary_search_by(|header| header.file_name.as_str().cmp(file_name))
+        .ok()
+        .map(|i| &headers[i])
+}
+
+/// Remove headers with the same file name as a former one.
+pub fn deduplicate_by_name(
+    headers: Vec<CentralDirectoryFileHeader>,
+) -> Vec<CentralDirectoryFileHeader> {
+    let mut found = HashSet::new();
+    headers
+        .into_iter()
+        .filter(|header| found.insert(UniqueByName(header.clone())))
+        .collect()
+}
+
+/// Detect the single top-level directory shared by all entries.
+/// Returns the prefix to strip including the trailing `/`, e.g. `project-1.0/`.
+pub fn detect_and_strip_top_dir(headers: &[CentralDirectoryFileHeader]) -> Option<String> {
+    let mut top_dir = None;
+    let mut has_nested_entry = false;
+    for header in headers {
+        let (first, rest) = header.file_name.split_once('/')?;
+        if first.is_empty() || first == "." || first == ".." {
+            return None;
+        }
+        match top_dir {
+            Some(top_dir) if top_dir != first => return None,
+            _ => top_dir = Some(first),
+        }
+        has_nested_entry |= !rest.is_empty();
+    }
+    if !has_nested_entry {
+        return None;
+    }
+    top_dir.map(|top_dir| format!("{}/", top_dir))
+}
+
+/// Whether the Unix file type in the external file attributes is a symbolic link (`S_IFLNK`).
+/// The attributes hold a Unix mode only if the entry is made on Unix.
+pub fn is_symlink_entry(header: &CentralDirectoryFileHeader) -> bool {
+    const UNIX: u16 = 3;
+    const S_IFMT: u32 = 0o170000;
+    const S_IFLNK: u32 = 0o120000;
+    header.version_made_by >> 8 == UNIX
+        && (header.external_file_attributes >> 16) & S_IFMT == S_IFLNK
+}
+
+/// Decode version made by into the OS and the ZIP specification version, e.g.) `("Unix", "2.0")`.
+pub fn decode_version_made_by(v: u16) -> (String, String) {
+    let os = match v >> 8 {
+        0 => "MS-DOS",
+        1 => "Amiga",
+        2 => "OpenVMS",
+        3 => "Unix",
+        4 => "VM/CMS",
+        5 => "Atari ST",
+        6 => "OS/2",
+        7 => "Macintosh",
+        8 => "Z-System",
+        9 => "CP/M",
+        10 => "NTFS",
+        11 => "MVS",
+        12 => "VSE",
+        13 => "Acorn Risc",
+        14 => "VFAT",
+        15 => "Alternate MVS",
+        16 => "BeOS",
+        17 => "Tandem",
+        18 => "OS/400",
+        19 => "macOS",
+        os => return (format!("Unknown({})", os), format_spec_version(v)),
+    };
+    (os.to_string(), format_spec_version(v))
+}
+
+fn format_spec_version(v: u16) -> String {
+    let spec_version = v & 0xff;
+    format!("{}.{}", spec_version / 10, spec_version % 10)
+}
+
+/// The most common OS which created the entries. Ties are broken by the first entry.
+pub fn archive_origin_os(headers: &[CentralDirectoryFileHeader]) -> Option<String> {
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    for (i, header) in headers.iter().enumerate() {
+        let (os, _) = decode_version_made_by(header.version_made_by);
+        counts.entry(os).or_insert((0, i)).0 += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|(_, (a, i)), (_, (b, j))| a.cmp(b).then(j.cmp(i)))
+        .map(|(os, _)| os)
+}
+
+impl From<&CentralDirectoryFileHeader> for FileEntry {
+    fn from(header: &CentralDirectoryFileHeader) -> Self {
+        FileEntry {
+            file_name: header.file_name.clone(),
+            uncompressed_size: u64::from(header.uncompressed_size),
+            crc32: header.crc32,
+            last_modified: header.last_modified,
+            is_directory: ZipFileReader::is_directory_entry(header),
+            version_made_by: Some(header.version_made_by),
+            is_encrypted: ZipFileReader::is_encrypted(header.general_purpose_bit_flag),
+        }
+    }
+}
+
+impl Iterator for CentralDirectoryIter<'_> {
+    type Item = Result<CentralDirectoryFileHeader>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        match self
+            .zip
+            .read_central_directory_file_header_at(self.position)
+        {
+            Ok((header, next_offset)) => {
+                self.position = next_offset;
+                Some(header)
+            }
+            Err(e) => {
+                // The position of the next header is unknown.
+                self.remaining = 0;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        archive_origin_os, decode_version_made_by, deduplicate_by_name, detect_and_strip_top_dir,
+        find_by_file_name, is_symlink_entry, sort_by_file_name, CentralDirectoryFileHeader,
+        CompressionMethod, UniqueByName, ZipFileReader,
+    };
+    use crate::archive::FileEntry;
+    use std::cmp::Ordering;
+    use std::fs;
+
+    /// Central directory file header with the fixed fields zeroed except the lengths.
+    fn central_directory_file_header(
+        file_name: &[u8],
+        extra_field: &[u8],
+        comment: &[u8],
+    ) -> Vec<u8> {
+        let mut header = vec![0x50, 0x4b, 0x01, 0x02];
+        header.extend([0u8; 24]);
+        header.extend((file_name.len() as u16).to_le_bytes());
+        header.extend((extra_field.len() as u16).to_le_bytes());
+        header.extend((comment.len() as u16).to_le_bytes());
+        header.extend([0u8; 12]);
+        header.extend(file_name);
+        header.extend(extra_field);
+        header.extend(comment);
+        header
+    }
+
+    #[test]
+    fn advance_past_extra_field_and_comment() {
+        let first = central_directory_file_header(b"a.txt", &[0xff; 9], b"first comment");
+        let second = central_directory_file_header(b"dir/b.txt", &[], b"");
+        let third = central_directory_file_header(b"c.txt", &[0x01, 0x02, 0x03], b"c");
+        let mut archive = Vec::new();
+        for header in [&first, &second, &third] {
+            archive.extend(header);
+        }
+        // 46 bytes of fixed fields followed by the variable length fields.
+        assert_eq!(46 + 5 + 9 + 13, first.len());
+        archive.extend(ZipFileReader::END_OF_CENTRAL_DIR_SIGNATURE);
+        archive.extend([0u8; 4]);
+        archive.extend(3u16.to_le_bytes());
+        archive.extend(3u16.to_le_bytes());
+        archive.extend((archive.len() as u32 - 12).to_le_bytes());
+        archive.extend(0u32.to_le_bytes());
+        archive.extend(0u16.to_le_bytes());
+        let path = std::env::temp_dir().join("rmext_advance_past_extra_field_and_comment.zip");
+        fs::write(&path, archive).unwrap();
+
+        let mut reader = ZipFileReader::new(&path, "utf8".to_string()).unwrap();
+        let first_offset = reader.read_central_directory_file_header_at(0).unwrap().1;
+        let second_offset = reader
+            .read_central_directory_file_header_at(first_offset)
+            .unwrap()
+            .1;
+        reader.seek_end_of_central_directory_record().unwrap();
+        let names = reader
+            .entries()
+            .unwrap()
+            .map(|header| header.unwrap().file_name)
+            .collect::<Vec<_>>();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(first.len() as u64, first_offset);
+        assert_eq!((first.len() + second.len()) as u64, second_offset);
+        assert_eq!(vec!["a.txt", "dir/b.txt", "c.txt"], names);
+    }
+
+    #[test]
+    fn iterate_central_directory_file_headers() {
+        let mut reader = ZipFileReader::new("resource/archive2.zip", "utf8".to_string()).unwrap();
+        reader.seek_end_of_central_directory_record().unwrap();
+        let names = reader
+            .entries()
+            .unwrap()
+            .map(|header| header.unwrap().file_name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["file.txt".to_string()], names);
+    }
+
+    #[test]
+    fn seek_end_of_central_directory_record_with_comment() {
+        let comment = b"archive comment";
+        let mut archive = vec![0u8; 100];
+        archive.extend(ZipFileReader::END_OF_CENTRAL_DIR_SIGNATURE);
+        archive.extend([0u8; 16]);
+        archive.extend((comment.len() as u16).to_le_bytes());
+        archive.extend(comment);
+        let path = std::env::temp_dir().join("rmext_seek_eocd_with_comment.zip");
+        fs::write(&path, archive).unwrap();
+
+        let mut reader = ZipFileReader::new(&path, "utf8".to_string()).unwrap();
+        reader.seek_end_of_central_directory_record().unwrap();
+        let position = reader.stream_position().unwrap();
+        let end_of_central_directory = reader.end_of_central_directory().cloned().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(100, position);
+        assert_eq!("archive comment", end_of_central_directory.comment);
+        assert_eq!(
+            "Entries: 0 | CD Offset: 0 | Comment: \"archive comment\"",
+            end_of_central_directory.to_string()
+        );
+    }
+
+    #[test]
+    fn dump_end_of_central_directory_record() {
+        let mut reader = ZipFileReader::new("resource/archive2.zip", "utf8".to_string()).unwrap();
+        let dump = reader.dump_eocd().unwrap();
+        assert_eq!(22, dump.bytes.len());
+        assert_eq!(
+            ZipFileReader::END_OF_CENTRAL_DIR_SIGNATURE,
+            dump.bytes[0..4]
+        );
+        assert_eq!(
+            1,
+            dump.end_of_central_directory
+                .total_number_of_central_directory_records
+        );
+        assert!(dump.zip64_locator.is_none());
+        assert!(dump.to_string().starts_with("EOCD at "));
+    }
+
+    #[test]
+    fn clone_reader_at_same_position() {
+        let mut reader = ZipFileReader::new("resource/archive2.zip", "utf8".to_string()).unwrap();
+        reader.seek_end_of_central_directory_record().unwrap();
+        let mut clone = reader.try_clone().unwrap();
+        assert_eq!(
+            reader.stream_position().unwrap(),
+            clone.stream_position().unwrap()
+        );
+        assert_eq!(
+            reader.read_central_directory_file_header().unwrap(),
+            clone.read_central_directory_file_header().unwrap()
+        );
+    }
+
+    #[test]
+    fn read_local_file_header() {
+        let mut reader = ZipFileReader::new("resource/archive2.zip", "utf8".to_string()).unwrap();
+        reader.seek_end_of_central_directory_record().unwrap();
+        let header = reader
+            .read_central_directory_file_header()
+            .unwrap()
+            .remove(0);
+        let local_file_header = reader
+            .read_local_file_header_at(u64::from(header.relative_offset_of_local_header))
+            .unwrap();
+        assert_eq!(header.file_name, local_file_header.file_name);
+        assert!(reader.read_local_file_header_at(1).is_err());
+    }
+
+    #[test]
+    fn probe_file_name_encoding() {
+        assert_eq!(
+            Some("cp932"),
+            ZipFileReader::probe_encoding("resource/フォルダー.zip", 50).unwrap()
+        );
+        assert_eq!(
+            None,
+            ZipFileReader::probe_encoding("resource/archive2.zip", 50).unwrap()
+        );
+    }
+
+    #[test]
+    fn read_cp932_file_name() {
+        let mut reader =
+            ZipFileReader::new("resource/フォルダー.zip", "cp932".to_string()).unwrap();
+        reader.seek_end_of_central_directory_record().unwrap();
+        let headers = reader.read_central_directory_file_header().unwrap();
+        assert_eq!(1, headers.len());
+        assert_eq!("フォルダー/テキスト.txt", headers[0].file_name);
+    }
+
+    #[test]
+    fn sort_and_search_headers_by_name() {
+        let header = |file_name: &str, uncompressed_size| CentralDirectoryFileHeader {
+            file_name: file_name.to_string(),
+            uncompressed_size,
+            ..Default::default()
+        };
+        let mut headers = [header("c.txt", 1), header("a.txt", 3), header("b/d.txt", 2)];
+        sort_by_file_name(&mut headers);
+        let names = headers
+            .iter()
+            .map(|header| header.file_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a.txt", "b/d.txt", "c.txt"], names);
+        let found = find_by_file_name(&headers, "b/d.txt").unwrap();
+        assert_eq!(2, found.uncompressed_size);
+        assert!(find_by_file_name(&headers, "b.txt").is_none());
+    }
+
+    #[test]
+    fn order_unique_by_name_consistently_with_eq() {
+        let header = |file_name: &str, uncompressed_size| {
+            UniqueByName(CentralDirectoryFileHeader {
+                file_name: file_name.to_string(),
+                uncompressed_size,
+                ..Default::default()
+            })
+        };
+        assert_eq!(header("a.txt", 1), header("a.txt", 2));
+        assert_eq!(Ordering::Equal, header("a.txt", 1).cmp(&header("a.txt", 2)));
+        assert!(header("a.txt", 2) < header("b.txt", 1));
+    }
+
+    #[test]
+    fn detect_directory_entry() {
+        let header = |file_name: &str, uncompressed_size| CentralDirectoryFileHeader {
+            file_name: file_name.to_string(),
+            uncompressed_size,
+            ..Default::default()
+        };
+        assert!(ZipFileReader::is_directory_entry(&header("dir/", 0)));
+        assert!(!ZipFileReader::is_directory_entry(&header(
+            "dir/file.txt",
+            0
+        )));
+        assert!(!ZipFileReader::is_directory_entry(&header("dir/", 1)));
+    }
+
+    #[test]
+    fn detect_encrypted_entry() {
+        assert!(ZipFileReader::is_encrypted([0x01, 0x00]));
+        assert!(ZipFileReader::is_encrypted([0x09, 0x08]));
+        assert!(!ZipFileReader::is_encrypted([0x08, 0x08]));
+        let header = CentralDirectoryFileHeader {
+            general_purpose_bit_flag: [0x01, 0x00],
+            ..Default::default()
+        };
+        assert!(FileEntry::from(&header).is_encrypted);
+    }
+
+    #[test]
+    fn detect_single_top_dir() {
+        let headers = |file_names: &[&str]| {
+            file_names
+                .iter()
+                .map(|file_name| CentralDirectoryFileHeader {
+                    file_name: file_name.to_string(),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            Some("project-1.0/".to_string()),
+            detect_and_strip_top_dir(&headers(&[
+                "project-1.0/",
+                "project-1.0/src/main.rs",
+                "project-1.0/README.md"
+            ]))
+        );
+        assert_eq!(
+            None,
+            detect_and_strip_top_dir(&headers(&["project/src/main.rs", "README.md"]))
+        );
+        assert_eq!(
+            None,
+            detect_and_strip_top_dir(&headers(&["a/src/main.rs", "b/README.md"]))
+        );
+        assert_eq!(None, detect_and_strip_top_dir(&headers(&["empty/"])));
+    }
+
+    #[test]
+    fn deduplicate_headers_by_name() {
+        let header = |file_name: &str, uncompressed_size| CentralDirectoryFileHeader {
+            file_name: file_name.to_string(),
+            uncompressed_size,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![header("a.txt", 1), header("b.txt", 2)],
+            deduplicate_by_name(vec![
+                header("a.txt", 1),
+                header("b.txt", 2),
+                header("a.txt", 3)
+            ])
+        );
+    }
+
+    #[test]
+    fn display_header_summary() {
+        let mut header = CentralDirectoryFileHeader {
+            file_name: "src/main.rs".to_string(),
+            uncompressed_size: 4321,
+            crc32: Some(0xdeadbeef),
+            compression_method: Some(CompressionMethod::from(8)),
+            ..Default::default()
+        };
+        assert_eq!(
+            "src/main.rs (4321 bytes, crc32: deadbeef, method: Deflate)",
+            header.to_string()
+        );
+        header.crc32 = None;
+        header.compression_method = None;
+        assert_eq!("src/main.rs (4321 bytes)", header.to_string());
+    }
+
+    #[test]
+    fn detect_symlink_entry() {
+        let header = |mode: u32| CentralDirectoryFileHeader {
+            version_made_by: 0x0314,
+            external_file_attributes: mode << 16,
+            ..Default::default()
+        };
+        assert!(is_symlink_entry(&header(0o120777)));
+        assert!(!is_symlink_entry(&header(0o100644)));
+        assert!(!is_symlink_entry(&header(0o040755)));
+        // MS-DOS archive attribute only.
+        assert!(!is_symlink_entry(&CentralDirectoryFileHeader {
+            external_file_attributes: 0x20,
+            ..Default::default()
+        }));
+        // The same bits made on NTFS are not a Unix mode.
+        assert!(!is_symlink_entry(&CentralDirectoryFileHeader {
+            version_made_by: 0x0a14,
+            external_file_attributes: 0o120777 << 16,
+            ..Default::default()
+        }));
+
+        let mut bytes = central_directory_file_header(b"link", &[], b"");
+        bytes[4..6].copy_from_slice(&0x0314u16.to_le_bytes());
+        bytes[38..42].copy_from_slice(&(0o120777u32 << 16).to_le_bytes());
+        let path = std::env::temp_dir().join("rmext_detect_symlink_entry.zip");
+        fs::write(&path, bytes).unwrap();
+        let mut reader = ZipFileReader::new(&path, "utf8".to_string()).unwrap();
+        let parsed = reader.read_central_directory_file_header_at(0).unwrap().0;
+        fs::remove_file(&path).unwrap();
+        assert!(is_symlink_entry(&parsed.unwrap()));
+    }
+
+    #[test]
+    fn decode_os_and_version_made_by() {
+        assert_eq!(
+            ("Unix".to_string(), "2.0".to_string()),
+            decode_version_made_by(0x0314)
+        );
+        assert_eq!(
+            ("MS-DOS".to_string(), "6.3".to_string()),
+            decode_version_made_by(0x003f)
+        );
+        assert_eq!("Unknown(30)", decode_version_made_by(0x1e14).0);
+
+        let header = |version_made_by| CentralDirectoryFileHeader {
+            version_made_by,
+            ..Default::default()
+        };
+        assert_eq!(
+            Some("NTFS".to_string()),
+            archive_origin_os(&[header(0x0314), header(0x0a14), header(0x0a3f)])
+        );
+        assert_eq!(
+            Some("Unix".to_string()),
+            archive_origin_os(&[header(0x0314), header(0x0a14)])
+        );
+        assert_eq!(None, archive_origin_os(&[]));
+    }
+
+    #[test]
+    fn force_encoding_ignoring_utf8_flag() {
+        let mut header = central_directory_file_header(b"\x83e\x83L\x83X\x83g.txt", &[], b"");
+        // Bit 11 of the general purpose bit flag.
+        header[9] |= 0x08;
+        let mut archive = header;
+        archive.extend(ZipFileReader::END_OF_CENTRAL_DIR_SIGNATURE);
+        archive.extend([0u8; 4]);
+        archive.extend(1u16.to_le_bytes());
+        archive.extend(1u16.to_le_bytes());
+        archive.extend((archive.len() as u32 - 12).to_le_bytes());
+        archive.extend(0u32.to_le_bytes());
+        archive.extend(0u16.to_le_bytes());
+        let path = std::env::temp_dir().join("rmext_force_encoding_ignoring_utf8_flag.zip");
+        fs::write(&path, archive).unwrap();
+
+        let mut reader = ZipFileReader::new(&path, "cp932".to_string()).unwrap();
+        reader.seek_end_of_central_directory_record().unwrap();
+        let trusted = reader.read_central_directory_file_header();
+        reader.set_force_encoding(Some("cp932".to_string()));
+        let forced = reader.read_central_directory_file_header().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(trusted.is_err());
+        assert_eq!("テキスト.txt", forced[0].file_name);
+        assert_eq!("cp932", forced[0].file_name_encoding);
+    }
+
+    #[test]
+    fn decode_comment_independently_from_file_names() {
+        let mut header = central_directory_file_header("テキスト.txt".as_bytes(), &[], b"");
+        header[9] |= 0x08;
+        let comment = b"\x83R\x83\x81\x83\x93\x83g";
+        let mut archive = header;
+        archive.extend(ZipFileReader::END_OF_CENTRAL_DIR_SIGNATURE);
+        archive.extend([0u8; 4]);
+        archive.extend(1u16.to_le_bytes());
+        archive.extend(1u16.to_le_bytes());
+        archive.extend((archive.len() as u32 - 12).to_le_bytes());
+        archive.extend(0u32.to_le_bytes());
+        archive.extend((comment.len() as u16).to_le_bytes());
+        archive.extend(comment);
+        let path = std::env::temp_dir().join("rmext_decode_comment_independently.zip");
+        fs::write(&path, archive).unwrap();
+
+        let mut reader = ZipFileReader::new(&path, "utf8".to_string()).unwrap();
+        let lossy_comment = reader.read_archive_comment().unwrap();
+        reader.set_comment_encoding(Some("cp932".to_string()));
+        let comment = reader.read_archive_comment().unwrap();
+        let headers = reader.read_central_directory_file_header().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_ne!("コメント", lossy_comment);
+        assert_eq!("コメント", comment);
+        assert_eq!("テキスト.txt", headers[0].file_name);
     }
 }
@@ -0,0 +1,147 @@
+use std::fmt;
+use std::io::Result;
+
+use crate::archive::zip::reader::ZipFileReader;
+
+/// Result of checking that a zip file is self-consistent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationReport {
+    Consistent,
+    /// Descriptions of the problems found.
+    Inconsistency(Vec<String>),
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationReport::Consistent => write!(f, "consistent"),
+            ValidationReport::Inconsistency(problems) => write!(f, "{}", problems.join(", ")),
+        }
+    }
+}
+
+/// Check the position of the EOCD, signatures of central directory file headers and
+/// local file headers, file names of local file headers and the number of entries recorded
+/// in the EOCD.
+pub fn validate_zip_consistency(reader: &mut ZipFileReader) -> Result<ValidationReport> {
+    let mut problems = Vec::new();
+
+    reader.seek_end_of_central_directory_record()?;
+    let eocd_offset = reader.stream_position()?;
+    let eocd = reader
+        .end_of_central_directory()
+        .cloned()
+        .unwrap_or_default();
+    let total_entries = usize::from(eocd.total_number_of_central_directory_records);
+
+    // The comment must end at the end of file.
+    let comment_length = reader.read_bytes_at(eocd_offset + 20, 2)?;
+    let eocd_end =
+        eocd_offset + 22 + u64::from(u16::from_le_bytes([comment_length[0], comment_length[1]]));
+    if eocd_end != reader.file_size() {
+        problems.push(format!(
+            "EOCD ends at {} but the file size is {}",
+            eocd_end,
+            reader.file_size()
+        ));
+    }
+    // The central directory is followed by the EOCD. The values are in the ZIP64 EOCD if ZIP64.
+    let central_directory_end = u64::from(eocd.offset_of_start_of_central_directory)
+        + u64::from(eocd.size_of_central_directory);
+    if !eocd.is_zip64() && central_directory_end != eocd_offset {
+        problems.push(format!(
+            "central directory ends at {} but EOCD is at {}",
+            central_directory_end, eocd_offset
+        ));
+    }
+
+    reader.seek_end_of_central_directory_record()?;
+    let mut headers = Vec::new();
+    let mut entry_count = 0;
+    for header in reader.entries()? {
+        entry_count += 1;
+        match header {
+            Ok(header) => headers.push(header),
+            Err(e) => problems.push(e.to_string()),
+        }
+    }
+    if entry_count != total_entries {
+        problems.push(format!(
+            "EOCD records {} entries but {} central directory entries were read",
+            total_entries, entry_count
+        ));
+    }
+
+    for header in &headers {
+        let offset = u64::from(header.relative_offset_of_local_header);
+        match reader.read_local_file_header_at(offset) {
+            Ok(local_file_header) if local_file_header.file_name != header.file_name => problems
+                .push(format!(
+                    "file name of local file header at {} does not match: {} != {}",
+                    offset, local_file_header.file_name, header.file_name
+                )),
+            Ok(_) => {}
+            Err(e) => problems.push(format!("{}: {}", header.file_name, e)),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(ValidationReport::Consistent)
+    } else {
+        Ok(ValidationReport::Inconsistency(problems))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_zip_consistency, ValidationReport};
+    use crate::archive::zip::reader::ZipFileReader;
+    use std::fs;
+
+    #[test]
+    fn validate_consistent_zip() {
+        let mut reader = ZipFileReader::new("resource/archive2.zip", "utf8".to_string()).unwrap();
+        assert_eq!(
+            ValidationReport::Consistent,
+            validate_zip_consistency(&mut reader).unwrap()
+        );
+    }
+
+    #[test]
+    fn ignore_eocd_signature_in_file_data() {
+        let mut reader =
+            ZipFileReader::new("resource/eocd_in_data.zip", "utf8".to_string()).unwrap();
+        assert_eq!(
+            ValidationReport::Consistent,
+            validate_zip_consistency(&mut reader).unwrap()
+        );
+    }
+
+    #[test]
+    fn detect_wrong_central_directory_size() {
+        let mut archive = fs::read("resource/archive2.zip").unwrap();
+        // Size of the central directory in the EOCD.
+        let size_offset = archive.len() - 22 + 12;
+        archive[size_offset] -= 1;
+        let path = std::env::temp_dir().join("rmext_detect_wrong_central_directory_size.zip");
+        fs::write(&path, archive).unwrap();
+
+        let mut reader = ZipFileReader::new(&path, "utf8".to_string()).unwrap();
+        let report = validate_zip_consistency(&mut reader).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(report, ValidationReport::Inconsistency(_)));
+    }
+
+    #[test]
+    fn detect_broken_local_file_header() {
+        let mut archive = fs::read("resource/archive2.zip").unwrap();
+        archive[0] = 0;
+        let path = std::env::temp_dir().join("rmext_detect_broken_local_file_header.zip");
+        fs::write(&path, archive).unwrap();
+
+        let mut reader = ZipFileReader::new(&path, "utf8".to_string()).unwrap();
+        let report = validate_zip_consistency(&mut reader).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(report, ValidationReport::Inconsistency(problems) if problems.len() == 1));
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archive::FileEntry;
+use crate::util::digest::{crc32_file, CrcCache};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Exists with the same size and CRC-32.
+    Present,
+    Missing,
+    SizeMismatch,
+    CrcMismatch,
+    /// Exists on disk in an extracted directory but not in the archive.
+    Extra,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            CheckStatus::Present => "PRESENT",
+            CheckStatus::Missing => "MISSING",
+            CheckStatus::SizeMismatch => "SIZE_MISMATCH",
+            CheckStatus::CrcMismatch => "CRC_MISMATCH",
+            CheckStatus::Extra => "EXTRA",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckEntry {
+    pub path: PathBuf,
+    pub status: CheckStatus,
+    /// Size on disk.
+    pub size: Option<u64>,
+    /// CRC-32 on disk.
+    pub crc32: Option<u32>,
+}
+
+impl fmt::Display for CheckEntry {
+    /// e.g.) `PRESENT dir/file.txt [size: 3, crc: 352441c2]`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.status, self.path.to_string_lossy())?;
+        match (self.size, self.crc32) {
+            (Some(size), Some(crc32)) => write!(f, " [size: {}, crc: {:08x}]", size, crc32),
+            (Some(size), None) => write!(f, " [size: {}]", size),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Result of comparing archive contents with files on disk. Includes all statuses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    pub entries: Vec<CheckEntry>,
+}
+
+impl CheckReport {
+    /// Whether all archive contents are extracted as they are.
+    pub fn is_complete(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| entry.status == CheckStatus::Present)
+    }
+}
+
+/// Compare archive contents with the paths where they are expected to be extracted.
+/// Files in the directories containing the contents, other than `search_path`, which are not in
+/// the archive are reported as extra.
+/// CRC-32 on disk is looked up in `crc_cache` if given.
+pub fn check_extraction(
+    contents: &[(PathBuf, FileEntry)],
+    search_path: &Path,
+    mut crc_cache: Option<&mut CrcCache>,
+) -> CheckReport {
+    let mut entries = Vec::new();
+    let mut content_paths = HashSet::new();
+    let mut directories = HashSet::new();
+    for (path, entry) in contents {
+        content_paths.insert(path.clone());
+        if let Some(parent) = path.parent().filter(|parent| *parent != search_path) {
+            directories.insert(parent.to_path_buf());
+        }
+        if entry.is_directory {
+            continue;
+        }
+        let Some(metadata) = fs::metadata(path)
+            .ok()
+            .filter(|metadata| metadata.is_file())
+        else {
+            entries.push(CheckEntry {
+                path: path.clone(),
+                status: CheckStatus::Missing,
+                size: None,
+                crc32: None,
+            });
+            continue;
+        };
+        let size = metadata.len();
+        if size != entry.uncompressed_size {
+            entries.push(CheckEntry {
+                path: path.clone(),
+                status: CheckStatus::SizeMismatch,
+                size: Some(size),
+                crc32: None,
+            });
+            continue;
+        }
+        let crc32 = match crc_cache.as_deref_mut() {
+            Some(crc_cache) => crc_cache.crc32(path),
+            None => crc32_file(path),
+        }
+        .ok();
+        let status = match entry.crc32 {
+            Some(expected) if crc32 != Some(expected) => CheckStatus::CrcMismatch,
+            _ => CheckStatus::Present,
+        };
+        entries.push(CheckEntry {
+            path: path.clone(),
+            status,
+            size: Some(size),
+            crc32,
+        });
+    }
+
+    let mut directories = Vec::from_iter(directories);
+    directories.sort();
+    for directory in directories {
+        let Ok(read_dir) = fs::read_dir(&directory) else {
+            continue;
+        };
+        let mut extra_paths: Vec<PathBuf> = read_dir
+            .filter_map(|dir_entry| dir_entry.ok())
+            .map(|dir_entry| dir_entry.path())
+            .filter(|path| path.is_file() && !content_paths.contains(path))
+            .collect();
+        extra_paths.sort();
+        entries.extend(extra_paths.into_iter().map(|path| CheckEntry {
+            size: fs::metadata(&path).ok().map(|metadata| metadata.len()),
+            path,
+            status: CheckStatus::Extra,
+            crc32: None,
+        }));
+    }
+    CheckReport { entries }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_extraction, CheckStatus};
+    use crate::archive::FileEntry;
+    use std::fs;
+
+    #[test]
+    fn check_extracted_files() {
+        let dir = std::env::temp_dir().join("rmext_check_extracted_files");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/present.txt"), "abc").unwrap();
+        fs::write(dir.join("sub/size.txt"), "abcd").unwrap();
+        fs::write(dir.join("sub/crc.txt"), "abd").unwrap();
+        fs::write(dir.join("sub/extra.txt"), "").unwrap();
+        let entry = |file_name: &str| {
+            (
+                dir.join(file_name),
+                FileEntry {
+                    file_name: file_name.to_string(),
+                    uncompressed_size: 3,
+                    crc32: Some(0x352441c2),
+                    ..Default::default()
+                },
+            )
+        };
+        let contents = vec![
+            entry("sub/present.txt"),
+            entry("sub/size.txt"),
+            entry("sub/crc.txt"),
+            entry("sub/missing.txt"),
+        ];
+
+        let report = check_extraction(&contents, &dir, None);
+        fs::remove_dir_all(&dir).unwrap();
+        let statuses = report
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.path.strip_prefix(&dir).unwrap().to_path_buf(),
+                    entry.status,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("sub/present.txt".into(), CheckStatus::Present),
+                ("sub/size.txt".into(), CheckStatus::SizeMismatch),
+                ("sub/crc.txt".into(), CheckStatus::CrcMismatch),
+                ("sub/missing.txt".into(), CheckStatus::Missing),
+                ("sub/extra.txt".into(), CheckStatus::Extra),
+            ],
+            statuses
+        );
+        let mut present = report.entries[0].clone();
+        present.path = "sub/present.txt".into();
+        assert_eq!(
+            "PRESENT sub/present.txt [size: 3, crc: 352441c2]",
+            present.to_string()
+        );
+        assert!(!report.is_complete());
+    }
+}
@@ -0,0 +1,52 @@
+/// Supported encodings: the name for `--encoding`, its aliases and a description.
+pub const ENCODINGS: &[(&str, &[&str], &str)] = &[
+    ("utf8", &["utf-8"], "Unicode UTF-8"),
+    (
+        "cp932",
+        &["shift_jis", "sjis", "windows-31j"],
+        "Microsoft Code Page 932, Japanese",
+    ),
+];
+
+/// Name for `--encoding` of the name or the alias. Case insensitive.
+pub fn canonical_encoding(name: &str) -> Option<&'static str> {
+    ENCODINGS
+        .iter()
+        .find(|(canonical, aliases, _)| {
+            canonical.eq_ignore_ascii_case(name)
+                || aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+        })
+        .map(|(canonical, _, _)| *canonical)
+}
+
+/// Tab separated lines of the name, the comma separated aliases and the description.
+pub fn format_encodings() -> String {
+    ENCODINGS
+        .iter()
+        .map(|(canonical, aliases, description)| {
+            format!("{}\t{}\t{}\n", canonical, aliases.join(","), description)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_encoding, format_encodings};
+
+    #[test]
+    fn resolve_encoding_aliases() {
+        assert_eq!(Some("utf8"), canonical_encoding("UTF-8"));
+        assert_eq!(Some("cp932"), canonical_encoding("Shift_JIS"));
+        assert_eq!(Some("cp932"), canonical_encoding("cp932"));
+        assert_eq!(None, canonical_encoding("latin1"));
+    }
+
+    #[test]
+    fn format_encoding_table() {
+        assert_eq!(
+            "utf8\tutf-8\tUnicode UTF-8\n\
+             cp932\tshift_jis,sjis,windows-31j\tMicrosoft Code Page 932, Japanese\n",
+            format_encodings()
+        );
+    }
+}
@@ -0,0 +1,149 @@
+pub mod archive;
+pub mod check;
+pub mod config;
+pub mod output;
+pub mod remover;
+pub mod util;
+pub mod version_info;
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Result, Write};
+use std::path::{Path, PathBuf};
+
+/// File name of the manifest written into the backup directory.
+pub const BACKUP_MANIFEST_FILE_NAME: &str = "rmext_manifest.jsonl";
+
+/// A line of the backup manifest.
+/// `backup` is relative to the backup directory.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub original: PathBuf,
+    pub backup: PathBuf,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RestoreStats {
+    pub restored: u64,
+    pub skipped: u64,
+    pub failed: u64,
+}
+
+/// Append a record to the backup manifest.
+pub fn append_backup_record(manifest: &Path, record: &BackupRecord) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(manifest)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
+
+/// Read the records of the backup manifest.
+pub fn read_backup_records(manifest: &Path) -> Result<Vec<BackupRecord>> {
+    let mut records = Vec::new();
+    for line in BufReader::new(fs::File::open(manifest)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str(&line)?);
+    }
+    Ok(records)
+}
+
+/// Move backed up files back to their original locations.
+/// Records which could not be restored are kept in the manifest.
+pub fn restore_backup(manifest: &Path, backup_dir: &Path, overwrite: bool) -> Result<RestoreStats> {
+    let mut stats = RestoreStats::default();
+    let mut remaining_records = Vec::new();
+    for record in read_backup_records(manifest)? {
+        if record.original.exists() && !overwrite {
+            println!(
+                "\t{} already exists. Skip restoring.",
+                record.original.to_string_lossy()
+            );
+            stats.skipped += 1;
+            remaining_records.push(record);
+            continue;
+        }
+        match move_file(&backup_dir.join(&record.backup), &record.original) {
+            Ok(_) => {
+                println!("\tRestored: {}.", record.original.to_string_lossy());
+                stats.restored += 1;
+            }
+            Err(e) => {
+                eprintln!(
+                    "Failed to restore {}: {}",
+                    record.original.to_string_lossy(),
+                    e
+                );
+                stats.failed += 1;
+                remaining_records.push(record);
+            }
+        }
+    }
+
+    if remaining_records.is_empty() {
+        fs::remove_file(manifest)?;
+    } else {
+        let mut file = fs::File::create(manifest)?;
+        for record in &remaining_records {
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
+        }
+    }
+    Ok(stats)
+}
+
+/// Move file, falling back to copy and remove across filesystems.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{append_backup_record, restore_backup, BackupRecord, RestoreStats};
+    use std::fs;
+
+    #[test]
+    fn restore_backed_up_files() {
+        let dir = std::env::temp_dir().join("rmext_restore_backed_up_files");
+        let backup_dir = dir.join("backup");
+        fs::create_dir_all(backup_dir.join("sub")).unwrap();
+        fs::write(backup_dir.join("sub/restored.txt"), "restored").unwrap();
+        fs::write(backup_dir.join("existing.txt"), "backup").unwrap();
+        fs::write(dir.join("existing.txt"), "re-extracted").unwrap();
+        let manifest = backup_dir.join("manifest.jsonl");
+        for name in ["sub/restored.txt", "existing.txt"] {
+            let record = BackupRecord {
+                original: dir.join(name),
+                backup: name.into(),
+            };
+            append_backup_record(&manifest, &record).unwrap();
+        }
+
+        let stats = restore_backup(&manifest, &backup_dir, false).unwrap();
+
+        let restored = fs::read_to_string(dir.join("sub/restored.txt")).unwrap();
+        let existing = fs::read_to_string(dir.join("existing.txt")).unwrap();
+        let remaining = fs::read_to_string(&manifest).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            RestoreStats {
+                restored: 1,
+                skipped: 1,
+                failed: 0
+            },
+            stats
+        );
+        assert_eq!("restored", restored);
+        assert_eq!("re-extracted", existing);
+        assert_eq!(1, remaining.lines().count());
+    }
+}
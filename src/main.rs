@@ -1,326 +1,4157 @@
-mod archive;
-use crate::archive::zip::reader;
-use archive::zip::reader::CentralDirectoryFileHeader;
+use anyhow::Context;
 use atty::Stream;
 use clap::CommandFactory;
-use clap::Parser;
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use rayon::prelude::*;
+use rmext::archive::zip::{
+    archive_origin_os, decode_version_made_by, detect_and_strip_top_dir, is_symlink_entry,
+    validate_zip_consistency, CentralDirectoryFileHeader, EndOfCentralDirectory, ValidationReport,
+    ZipFileReader,
+};
+use rmext::archive::{archive_format, detect_format, open_archive, ArchiveFormat, FileEntry};
+use rmext::check::{check_extraction, CheckStatus};
+use rmext::config::encodings::{canonical_encoding, format_encodings};
+use rmext::output::histogram::{format_histogram, size_histogram};
+use rmext::output::tree::render_tree;
+use rmext::output::{
+    extension_report, format_size, group_by_top_dir, print_line, print_path, write_atomically,
+    write_line, write_split_output,
+};
+use rmext::remover::{RealRemover, Remover, SimulatedRemover};
+use rmext::util::dedup::find_duplicate_names;
+use rmext::util::digest::{
+    compute_and_write_hashes, crc32_file, find_file_by_crc, parse_digest_file, sha256_file,
+    CrcCache,
+};
+use rmext::util::disk::check_disk_space;
+use rmext::util::display::truncate_path_display;
+use rmext::util::path::{
+    archive_dir, archive_path_relative_to_cwd, archive_stem, is_hidden_path, is_within_ignored_dir,
+};
+use rmext::util::sandbox::{check_sandbox, SandboxViolation};
+use rmext::util::time::format_timestamp;
+use rmext::version_info::format_version_info;
+use rmext::{
+    append_backup_record, read_backup_records, restore_backup, BackupRecord,
+    BACKUP_MANIFEST_FILE_NAME,
+};
 use std::{
-    collections::HashSet,
-    fs,
-    io::{self, Read, Result, Write},
-    path::{Path, PathBuf},
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufWriter, Error, ErrorKind, Read, Result, Write},
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant},
 };
 
 /// Simple program to delete the contents extracted from the archive.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+// `--print0` applies to the split output and the log of deleted paths.
+#[command(group(
+    ArgGroup::new("nul_output")
+        .args(["split_output", "log_deleted_paths_to"])
+        .multiple(true)
+))]
 struct Args {
-    /// Path to archive file.
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to archive file. Can be given multiple times.
+    /// ZIP (.zip, .docx, .xlsx, .pptx, .odt, .ods, .odp, .epub, .apk, .jar), LHA and RAR are supported.
+    /// Files with other extensions are read as ZIP if they start with a ZIP signature.
     #[arg(short, long)]
-    path: Option<String>,
+    path: Vec<String>,
+
+    /// Expand glob patterns in --path. Patterns of paths which do not exist are always expanded.
+    #[arg(long)]
+    glob_path: bool,
+
+    /// File listing paths to archive files, one per line.
+    #[arg(long)]
+    path_file: Option<PathBuf>,
 
-    /// Mode 1: file 2: directory 3: file and directory   
-    #[arg(long, short, default_value_t = 3)]
-    mode: u8,
+    /// Number of archives read concurrently. 0 uses the number of CPUs.
+    /// Headers of each archive being read are held in memory at the same time.
+    #[arg(long, default_value_t = 1)]
+    parallel_archives: usize,
+
+    /// Where to search archive contents. 1, 2 and 3 are also accepted.
+    #[arg(long, short, value_enum, default_value_t = Mode::Both, global = true)]
+    mode: Mode,
 
     /// Delete files interactively.
     #[arg(long, short)]
     interactive: bool,
 
+    /// Read archives as this format (zip, lha or rar) regardless of the extension and the signature.
+    #[arg(long, value_name = "TYPE", global = true)]
+    archive_type: Option<ArchiveFormat>,
+
     /// Character code used for encoding when Bit 11 of general purpose bit flag is 0.
-    #[arg(long, short, default_value = "utf8")]
+    #[arg(long, short, default_value = "utf8", global = true)]
     encoding: String,
 
+    /// Character code used to print paths. Only takes effect on Windows.
+    #[arg(long, default_value = "utf8")]
+    output_encoding: String,
+
+    /// Character code of the zip archive comment. Defaults to --encoding.
+    #[arg(long)]
+    archive_comment_encoding: Option<String>,
+
+    /// Character code of zip content file names used even if they are marked as UTF-8 (bit 11).
+    /// A workaround for archivers which set the flag on file names in a legacy encoding.
+    #[arg(long)]
+    force_encoding: Option<String>,
+
+    /// Guess the character code of zip content file names. --encoding is used if it can not be guessed.
+    #[arg(long)]
+    encoding_fallback: bool,
+
+    /// Number of entries examined to guess the character code with --encoding-fallback.
+    #[arg(long, value_name = "N", default_value_t = 50)]
+    encoding_probe_n: usize,
+
     /// Deletes directories that will be empty after a file is deleted.
     #[arg(long, short)]
     recursive: bool,
 
-    /// List archive contents.
+    /// Do not print "Remove empty directory recursively." with --recursive.
+    #[arg(long)]
+    no_recursive_warn: bool,
+
+    /// Maximum number of directory levels above each removed file to remove recursively.
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// List archive contents. With --verbose, a histogram of the file sizes follows the list.
     #[arg(long, short)]
     list: bool,
+
+    /// List all archive contents including ones which are not found on disk or differ in size.
+    /// Implies --list.
+    #[arg(long)]
+    list_all: bool,
+
+    /// Print verbose output.
+    #[arg(long, short, global = true)]
+    verbose: bool,
+
+    /// Strip the top-level directory shared by all zip contents when searching the directory
+    /// containing the archive (mode 1).
+    #[arg(long)]
+    strip_top_dir: bool,
+
+    /// Prepend this path to archive entry names when searching,
+    /// e.g.) `extracted/` for files extracted into a subdirectory. Applied after --strip-top-dir.
+    #[arg(long, value_name = "PATH")]
+    prefix: Option<String>,
+
+    /// Use raw archive entry names without normalization. Disables path traversal protection.
+    #[arg(long)]
+    no_normalize: bool,
+
+    /// Skip archive contents whose paths are longer than this number of bytes.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_PATH_LENGTH)]
+    max_path_length: usize,
+
+    /// Check write permissions of files before removing.
+    #[arg(long)]
+    check_permissions: bool,
+
+    /// Abort instead of asking to continue when a check fails.
+    #[arg(long)]
+    strict: bool,
+
+    /// Abort with exit code 3 unless every file in the archive exists on disk regardless of size,
+    /// since the extraction may be incomplete.
+    #[arg(long)]
+    verify_extraction_complete: bool,
+
+    /// Abort before removing when a zip file is not self-consistent.
+    #[arg(long)]
+    check_zip_before_delete: bool,
+
+    /// Also remove archives whose contents are found.
+    #[arg(long, conflicts_with = "keep_archive")]
+    delete_archive: bool,
+
+    /// Allow archive contents which are the archives themselves to be removed.
+    /// They are skipped by default since nothing is left to check what was removed.
+    #[arg(long)]
+    allow_delete_archive: bool,
+
+    /// Keep archives. This is the default.
+    #[arg(long)]
+    keep_archive: bool,
+
+    /// Checksum file written by sha256sum. Files listed in it are removed only if the checksum matches.
+    #[arg(long, value_name = "PATH")]
+    digest_file: Option<PathBuf>,
+
+    /// Move all files into a staging directory first and remove it at once.
+    /// Nothing is removed if moving any file fails.
+    #[arg(long)]
+    atomic_delete: bool,
+
+    /// Run the whole removal including retries and the recursive pass, but only print
+    /// the files and directories which would be removed. For debugging.
+    #[arg(long, conflicts_with_all = ["atomic_delete", "backup_dir"])]
+    simulate_readonly: bool,
+
+    /// Abort with exit code 5 if no answer is entered within this number of seconds
+    /// to each prompt with --interactive.
+    #[arg(long, value_name = "N", requires = "interactive")]
+    timeout_secs: Option<u64>,
+
+    /// Remove nothing unless the files to remove total at least this number of bytes.
+    #[arg(long, value_name = "BYTES")]
+    size_threshold: Option<u64>,
+
+    /// Prompt to confirm removing the files. `%n` is replaced with the number of files
+    /// and `%s` with the total size, e.g.) "Delete %n files (%s)? [Y/n] "
+    #[arg(long, default_value = DEFAULT_CONFIRM_MESSAGE)]
+    confirm_message: String,
+
+    /// Open the list of files to remove in $EDITOR to remove lines of files to keep.
+    /// Falls back to vi, or notepad on Windows. Aborts if the list is unchanged.
+    #[arg(long)]
+    interactive_editor: bool,
+
+    /// Stop removing at the first file which fails to be removed and exit with 4.
+    /// Empty directories are not removed then.
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Stop removing when this number of files fail to be removed and exit with 4.
+    #[arg(long, conflicts_with = "fail_fast")]
+    max_errors: Option<u64>,
+
+    /// Print the number of removed files and freed bytes by the file extension after removing.
+    #[arg(long)]
+    ext_report: bool,
+
+    /// Try removing the files which failed to be removed again, up to 3 times.
+    #[arg(long)]
+    rerun_on_partial: bool,
+
+    /// Shell command run after removing files without aborting, e.g.) for notifications.
+    /// `%n` is replaced with the number of removed files, `%s` with the removed bytes
+    /// and `%a` with the archive paths, each quoted. Run by sh -c, or cmd /C on Windows.
+    #[arg(long, value_name = "ACTION")]
+    completion_action: Option<String>,
+
+    /// Append the absolute paths of the files removed successfully to this file, one per line,
+    /// or terminated by NUL with `--print0`.
+    #[arg(long)]
+    log_deleted_paths_to: Option<PathBuf>,
+
+    /// Write SHA-256 of the files to remove to this file in the `sha256sum` format before removing.
+    /// Also written with --list.
+    #[arg(long)]
+    hash_file: Option<PathBuf>,
+
+    /// Never read archive paths from stdin even if it is a pipe. --path is required.
+    /// Also enabled by the environment variable RMEXT_NO_STDIN=1.
+    #[arg(long)]
+    no_stdin: bool,
+
+    /// Character separating archive paths read from stdin, e.g.) `:` or `;`.
+    #[arg(
+        long,
+        value_name = "CHAR",
+        default_value_t = '\n',
+        hide_default_value = true
+    )]
+    stdin_path_delimiter: char,
+
+    /// Print only the total size in bytes of the files to remove and exit without removing.
+    /// Exit with 1 if no files are found.
+    #[arg(long, conflicts_with_all = ["list_all", "only_empty_archives"])]
+    total_size_only: bool,
+
+    /// Print only the number of files to remove and exit without removing.
+    /// Exit with 3 if no files are found.
+    #[arg(
+        long,
+        conflicts_with_all = ["list_all", "only_empty_archives", "total_size_only"]
+    )]
+    count_only: bool,
+
+    /// Never remove directories on disk which match entries in the archive,
+    /// e.g.) with --only-empty-archives which keeps directory entries.
+    #[arg(long)]
+    preserve_dirs: bool,
+
+    /// Never list or remove files with a path component starting with `.`,
+    /// e.g.) `.gitignore`, `.git/config`
+    #[arg(long)]
+    skip_hidden: bool,
+
+    /// Never list or remove files in __pycache__, node_modules, .git, .svn or .hg directories
+    /// included in the archive by mistake.
+    #[arg(long)]
+    ignore_dot_zip_artifacts: bool,
+
+    /// How to decide that a file on disk is an archive content.
+    /// Strategies using CRC-32 fall back to name-and-size for contents without CRC-32.
+    #[arg(long, value_enum, default_value_t = MatchStrategy::NameAndSize)]
+    match_strategy: MatchStrategy,
+
+    /// Alias of `--match-strategy crc-only`.
+    #[arg(long, conflicts_with = "match_strategy")]
+    trust_crc: bool,
+
+    /// With --match-strategy crc-only, also match files of the same stem in the same directory,
+    /// e.g.) `a.bak` for `a.txt`. They are marked as renamed in the list.
+    #[arg(long)]
+    match_renamed: bool,
+
+    /// Skip zip contents which are symbolic links created on Unix.
+    /// Their size is the length of the link target, which may match an unrelated file.
+    #[arg(long)]
+    ignore_symlinks_in_archive: bool,
+
+    /// In mode 2, also try directories named like the archive stem, e.g.) lowercased or
+    /// without a version suffix, and search the one which matches the most files.
+    #[arg(long)]
+    guess_stem: bool,
+
+    /// Password of encrypted zip contents. Read from stdin if `-`.
+    /// Encrypted contents are skipped without it. They are not decrypted, so with it they are
+    /// matched without verifying CRC, and still skipped by a match strategy using CRC.
+    #[arg(long, value_name = "PWD")]
+    zip_password: Option<String>,
+
+    /// Alias of `--match-strategy name-and-size-or-crc`.
+    #[arg(long, conflicts_with_all = ["trust_crc", "match_strategy"])]
+    match_by_crc_first: bool,
+
+    /// List and remove files in the order of the archive entries instead of sorting by path.
+    #[arg(long)]
+    no_sort: bool,
+
+    /// Copy files into this directory before removing them.
+    #[arg(long)]
+    backup_dir: Option<PathBuf>,
+
+    /// Refuse to read, write or remove any file outside this directory, including the archives,
+    /// --path-file, the backup directory, the log and the output files. Symbolic links are resolved.
+    /// Also applies to the files restored by undo and the files read by check.
+    #[arg(long, value_name = "DIR")]
+    sandbox: Option<PathBuf>,
+
+    /// Check free disk space of the backup directory before copying.
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    disk_free_check: bool,
+
+    /// Skip removing archive contents and only remove directories left empty by them.
+    /// Implies --recursive.
+    #[arg(long)]
+    only_empty_archives: bool,
+
+    /// Show the number of files and total size by top-level directory instead of every file.
+    #[arg(long, requires = "list")]
+    directory_summary: bool,
+
+    /// Print ZIP metadata of the end of central directory record before the list.
+    #[arg(long)]
+    print_archive_info: bool,
+
+    /// Show the OS and the ZIP version which created each entry in the list.
+    #[arg(long)]
+    format_version: bool,
+
+    /// Truncate paths longer than this number of characters in the list.
+    #[arg(long, value_name = "N")]
+    truncate_names: Option<usize>,
+
+    /// Print the raw bytes and fields of the end of central directory record without removing.
+    #[arg(long, hide = true)]
+    dump_eocd: bool,
+
+    /// Show the list as a directory tree under the directory containing each archive.
+    #[arg(long, conflicts_with = "group_by_archive")]
+    print_tree: bool,
+
+    /// Mark files whose name is also found in another directory in the list,
+    /// e.g.) an archive extracted twice into different directories.
+    #[arg(long)]
+    list_duplicates: bool,
+
+    /// Write the list to this file instead of stdout. Prompts and progress are still printed.
+    #[arg(long, value_name = "PATH")]
+    output_file: Option<PathBuf>,
+
+    /// Write files to `<output-file>.files` and directories to `<output-file>.dirs`
+    /// instead of the list to the output file.
+    #[arg(long, requires = "output_file")]
+    split_output: bool,
+
+    /// Terminate paths in the split output and the log of deleted paths by NUL instead of newline.
+    #[arg(long, requires = "nul_output")]
+    print0: bool,
+
+    /// Separate the list by the archive which the files come from.
+    #[arg(long)]
+    group_by_archive: bool,
+
+    /// Show last modified time of archive contents in the list.
+    #[arg(long)]
+    timestamps: bool,
+
+    /// Show which encodings were used to decode file names in the list.
+    #[arg(long)]
+    encoding_report: bool,
 }
 
-const ALLOWED_ENCODINGS: &'static [&'static str] = &["utf8", "cp932"];
-const ALLOWED_CODES: &'static [u8] = &[1, 2, 3];
-
-fn main() -> Result<()> {
-    let args = Args::parse();
-    // let args = Args {
-    //     path: Some("resource/broken.zip".to_string()),
-    //     mode: 3,
-    //     interactive: true,
-    //     recursive: true,
-    //     encoding: "cp932".to_string(),
-    //     list: false,
-    // };
-    let archive_path = if args.path.is_none() {
-        if is_stdin(args.path.as_ref()) {
-            PathBuf::from(read_from_stdin().unwrap())
-        } else {
-            // Print help.
-            let mut cmd = Args::command();
-            let _ = cmd.print_help();
-            std::process::exit(1);
-        }
-    } else {
-        PathBuf::from(args.path.unwrap())
-    };
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Move files backed up by --backup-dir back to their original locations.
+    Undo {
+        /// Backup directory given to --backup-dir.
+        #[arg(long)]
+        backup_dir: PathBuf,
 
-    // Validate arguments.
-    assert!(ALLOWED_ENCODINGS.contains(&args.encoding.to_lowercase().as_ref()));
-    assert!(ALLOWED_CODES.contains(&args.mode));
-
-    let paths_to_delete = match archive_path.extension().unwrap().to_string_lossy().as_ref() {
-        "zip" => {
-            let mut reader = reader::ZipFileReader::new(&archive_path, args.encoding.to_string());
-            reader.seek_end_of_central_directory_record().unwrap();
-            let headers = reader.read_central_directory_file_header();
-            let mut codes = unpack_mode(args.mode);
-            codes.sort();
-            let mut paths_to_delete = Vec::new();
-            for code in &codes {
-                let search_path = match code {
-                    1 => archive_path.parent().unwrap().to_path_buf(),
-                    2 => Path::new(&archive_path.parent().unwrap())
-                        .join(archive_path.file_stem().unwrap()),
-                    _ => {
-                        eprintln!("invalid mode.");
-                        std::process::exit(1);
-                    }
-                };
-                let content_paths = search_zip_content_path_to_delete(&headers, &search_path);
-                paths_to_delete.extend(content_paths);
-            }
-            paths_to_delete.sort();
-            paths_to_delete
-        }
-        "rar" => search_rar_content_path_to_delete(
-            &archive_path,
-            &args.encoding,
-            &archive_path.parent().unwrap().to_path_buf(),
-        ),
-        _ => {
-            eprintln!("unsupported file type: {}", archive_path.to_string_lossy());
-            std::process::exit(1);
-        }
-    };
+        /// Overwrite files which already exist at the original location.
+        #[arg(long)]
+        overwrite_on_restore: bool,
+    },
+    /// Compare archive contents with the extracted files without removing.
+    Check {
+        /// Path to archive file.
+        archive: PathBuf,
 
-    if paths_to_delete.is_empty() {
-        println!("Archive contents are not found.");
-        println!("Skip removing.");
-        return Ok(());
-    }
+        /// Also list files which match the archive contents.
+        #[arg(long)]
+        show_unchanged: bool,
+
+        /// Cache CRC-32 of files on disk in this file to skip computing it for unchanged files
+        /// in later checks. Defaults to <archive>.rmext_crc_cache if no path is given.
+        /// Only used by this subcommand, not by the CRC match strategies of the removal.
+        #[arg(long, num_args = 0..=1)]
+        crc_cache: Option<Option<PathBuf>>,
+    },
+    /// Print the version with the build metadata.
+    Version,
+    /// Print the supported encodings: the name for --encoding, aliases and a description,
+    /// separated by tabs.
+    ListEncodings,
+}
+
+/// Where to search archive contents. The codes are bits of the directories to search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// Directory containing the archive.
+    #[value(alias = "1")]
+    Files = 1,
+    /// Directory named after the archive.
+    #[value(alias = "2")]
+    Directory = 2,
+    /// Both files and directory.
+    #[value(alias = "3")]
+    Both = 3,
+}
+
+impl FromStr for Mode {
+    type Err = String;
 
-    println!("The following files will be Removed:");
-    for delete_dir in &paths_to_delete {
-        println!("\t{}", delete_dir.to_string_lossy());
+    /// Parse the name or the code, e.g.) `both` or `3`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        <Mode as ValueEnum>::from_str(s, true)
     }
+}
 
-    if args.list {
-        println!("Skip removing.");
-        return Ok(());
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no skipped variants");
+        write!(f, "{}", value.get_name())
     }
+}
 
-    print!("Do you want to continue? [Y/n] ");
-    std::io::stdout().flush().unwrap();
+/// How to match archive contents with files on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum MatchStrategy {
+    /// Same name and size.
+    #[default]
+    NameAndSize,
+    /// Same name only. The least restrictive.
+    NameOnly,
+    /// Same CRC-32 regardless of size, e.g.) for files processed after extraction.
+    /// Files with the same stem in the same directory are also searched with --match-renamed.
+    /// Slow since CRC-32 of every candidate is computed.
+    CrcOnly,
+    /// Same name and CRC-32 regardless of size.
+    NameAndCrc,
+    /// Same name, size and CRC-32. The most restrictive.
+    NameSizeAndCrc,
+    /// Same name and either the same size or the same CRC-32.
+    /// Unlike name-and-crc, files of the same size match without computing CRC-32.
+    NameAndSizeOrCrc,
+}
 
-    let mut buffer;
-    if args.interactive {
-        buffer = String::new();
-        io::stdin()
-            .read_line(&mut buffer)
-            .expect("Failed to read line");
+impl MatchStrategy {
+    fn uses_crc(self) -> bool {
+        !matches!(self, MatchStrategy::NameAndSize | MatchStrategy::NameOnly)
+    }
+}
+
+/// The strategy given by --match-strategy or by its alias flags.
+fn match_strategy(args: &Args) -> MatchStrategy {
+    if args.trust_crc {
+        MatchStrategy::CrcOnly
+    } else if args.match_by_crc_first {
+        MatchStrategy::NameAndSizeOrCrc
     } else {
-        buffer = String::from("y");
-        println!("{}", buffer);
+        args.match_strategy
     }
+}
+
+/// Options for searching archive contents.
+#[derive(Clone)]
+struct SearchOptions {
+    normalize: bool,
+    verbose: bool,
+    /// Keep archive contents which do not exist on disk.
+    include_missing: bool,
+    max_path_length: usize,
+    match_strategy: MatchStrategy,
+    /// Skip directory entries and entries which are directories on disk.
+    preserve_dirs: bool,
+    /// Skip entries with a path component starting with `.`.
+    skip_hidden: bool,
+    /// Skip entries in directories of `IGNORED_ARTIFACT_DIR_NAMES`.
+    ignore_artifact_dirs: bool,
+    /// Normalized path prepended to entry names.
+    prefix: Option<String>,
+    /// Also match files of the same stem by CRC-32.
+    match_renamed: bool,
+}
 
-    if buffer.trim().to_lowercase() == "y" {
-        for path in &paths_to_delete {
-            remove_file(path);
+impl Default for SearchOptions {
+    /// Normalize names and match existing files by name and size.
+    fn default() -> Self {
+        SearchOptions {
+            normalize: true,
+            verbose: false,
+            include_missing: false,
+            max_path_length: DEFAULT_MAX_PATH_LENGTH,
+            match_strategy: MatchStrategy::NameAndSize,
+            preserve_dirs: false,
+            skip_hidden: false,
+            ignore_artifact_dirs: false,
+            prefix: None,
+            match_renamed: false,
         }
+    }
+}
+
+/// Counts of encodings used to decode zip content file names.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct EncodingReport {
+    utf8_flag: usize,
+    fallback: BTreeMap<&'static str, usize>,
+    replacement_chars: usize,
+    errors: usize,
+}
 
-        if args.recursive {
-            let mut ancestor_paths_to_delete = HashSet::new();
-            let parent = archive_path.parent().unwrap();
-            for path in &paths_to_delete {
-                for ancestor in path.ancestors() {
-                    if ancestor_paths_to_delete.contains(&ancestor) || parent == ancestor {
-                        break;
+impl EncodingReport {
+    fn new(headers: impl Iterator<Item = Result<CentralDirectoryFileHeader>>) -> EncodingReport {
+        let mut report = EncodingReport::default();
+        for header in headers {
+            match header {
+                Ok(header) => {
+                    if ZipFileReader::is_utf8(header.general_purpose_bit_flag) {
+                        report.utf8_flag += 1;
+                    } else {
+                        *report
+                            .fallback
+                            .entry(header.file_name_encoding)
+                            .or_default() += 1;
                     }
-                    if ancestor.is_dir() {
-                        ancestor_paths_to_delete.insert(ancestor);
+                    if header.file_name.contains('\u{FFFD}') {
+                        report.replacement_chars += 1;
                     }
                 }
+                Err(_) => report.errors += 1,
             }
+        }
+        report
+    }
 
-            if ancestor_paths_to_delete.is_empty() {
-                return Ok(());
-            }
+    fn print(&self) {
+        println!("Encoding report:");
+        println!("\tUTF-8 (Bit 11): {}", self.utf8_flag);
+        for (encoding, count) in &self.fallback {
+            println!("\tFallback ({}): {}", encoding, count);
+        }
+        println!("\tReplacement characters: {}", self.replacement_chars);
+        println!("\tDecoding errors: {}", self.errors);
+    }
+}
+
+/// Archive content found on disk.
+#[derive(Clone)]
+struct DeletionEntry {
+    path: PathBuf,
+    entry: FileEntry,
+    archive_path: PathBuf,
+}
 
-            println!("Remove empty directory recursively.");
-            let mut ancestor_paths_to_delete_sort_by_depth =
-                Vec::from_iter(ancestor_paths_to_delete);
-            sort_path_by_depth(&mut ancestor_paths_to_delete_sort_by_depth);
+/// State of an archive content on disk for --list-all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ListStatus {
+    Found,
+    NotFound,
+    /// Size on disk.
+    SizeMismatch(u64),
+}
 
-            for path in ancestor_paths_to_delete_sort_by_depth {
-                if !path.read_dir().unwrap().next().is_none() {
-                    println!(
-                        "\t{} is not empty. Skip removing.",
-                        path.to_string_lossy().into_owned()
-                    );
-                    continue;
-                }
-                remove_file(path);
-            }
+fn list_status(deletion_entry: &DeletionEntry) -> ListStatus {
+    let DeletionEntry { path, entry, .. } = deletion_entry;
+    match fs::metadata(path) {
+        Err(_) => ListStatus::NotFound,
+        Ok(metadata) if metadata.is_file() && metadata.len() != entry.uncompressed_size => {
+            ListStatus::SizeMismatch(metadata.len())
         }
+        Ok(_) => ListStatus::Found,
+    }
+}
+
+/// How to remove the files of a deletion plan.
+#[derive(Clone, Default)]
+struct DeletionConfig {
+    backup_dir: Option<PathBuf>,
+    atomic_delete: bool,
+    /// Remove empty directories after removing the files.
+    recursive: bool,
+    /// Do not print the message of removing empty directories.
+    no_recursive_warn: bool,
+    max_depth: Option<usize>,
+    /// File to append the paths of the removed files.
+    deleted_paths_log: Option<PathBuf>,
+    /// Terminate the paths in `deleted_paths_log` by NUL instead of newline.
+    print0: bool,
+    /// Stop at the first file which fails to be removed.
+    fail_fast: bool,
+    /// Stop when this number of files fail to be removed.
+    max_errors: Option<u64>,
+    /// List the files not attempted after stopping.
+    verbose: bool,
+    /// Only print the files which would be removed.
+    simulate_readonly: bool,
+}
+
+/// Remover which only prints the files if `simulate_readonly`.
+fn new_remover(simulate_readonly: bool) -> Box<dyn Remover> {
+    if simulate_readonly {
+        Box::new(SimulatedRemover::default())
     } else {
-        println!("Abort.");
+        Box::new(RealRemover)
     }
-    Ok(())
 }
 
-/// Detect stdin.
-fn is_stdin(input: Option<&String>) -> bool {
-    let is_request = match input {
-        Some(i) if i == "-" => true,
-        _ => false,
-    };
-    let is_pipe = !atty::is(Stream::Stdin);
-    is_request || is_pipe
+/// Files to remove decided before removing anything.
+struct DeletionPlan {
+    entries: Vec<DeletionEntry>,
+    /// Files and bytes to remove.
+    stats: RemovalStats,
+    config: DeletionConfig,
 }
 
-/// Read from stdin.
-fn read_from_stdin() -> Result<String> {
-    let mut buf = String::new();
-    let stdin = io::stdin();
-    let mut handle = stdin.lock();
-    handle.read_to_string(&mut buf)?;
-    Ok(buf.trim().to_string())
+#[derive(Debug, Default, PartialEq, Eq)]
+struct RemovalStats {
+    files: u64,
+    bytes: u64,
+    /// Files failed to be removed.
+    failed: u64,
+    /// Number of failures when removing stopped before all files were attempted.
+    errors_before_abort: Option<u64>,
+    /// Paths and sizes of the removed files.
+    removed: Vec<(PathBuf, u64)>,
+}
+
+/// Result of searching an archive.
+struct ArchiveSearch {
+    deletion_entries: Vec<DeletionEntry>,
+    encoding_report: Option<EncodingReport>,
+    /// End of central directory record and the most common OS which created the entries.
+    archive_info: Option<(EndOfCentralDirectory, Option<String>)>,
+    validation_report: Option<ValidationReport>,
 }
 
-/// Unpack mode to codes.
-fn unpack_mode(mode: u8) -> Vec<u32> {
-    let two: u8 = 2;
-    let mut code = 1;
-    let mut codes = Vec::new();
-    while mode >= two.pow(code - 1) {
-        if two.pow(code - 1) <= mode % two.pow(code) {
-            codes.push(code);
+/// Maximum path length of long path aware applications on Windows and PATH_MAX on Linux.
+const DEFAULT_MAX_PATH_LENGTH: usize = if cfg!(windows) { 32767 } else { 4096 };
+const ALLOWED_ENCODINGS: &[&str] = &["utf8", "cp932"];
+/// Directories of tools which are sometimes included in archives of project directories.
+const IGNORED_ARTIFACT_DIR_NAMES: &[&str] = &["__pycache__", "node_modules", ".git", ".svn", ".hg"];
+/// Exit code when removing files is aborted on failures.
+const EXIT_REMOVAL_ABORTED: i32 = 4;
+/// Exit code when archive contents are missing on disk with --verify-extraction-complete.
+const EXIT_EXTRACTION_INCOMPLETE: i32 = 3;
+/// Exit code when no files to remove are found with --count-only.
+const EXIT_NOTHING_FOUND: i32 = 3;
+/// Exit code when the confirmation prompt times out.
+const EXIT_USER_ABORTED: i32 = 5;
+/// Maximum number of times to retry removing files which failed with --rerun-on-partial.
+const MAX_RERUNS: usize = 3;
+const DEFAULT_CONFIRM_MESSAGE: &str = "Do you want to continue? [Y/n] ";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = Args::parse();
+    canonicalize_encodings(&mut args);
+    validate_encodings(&args)?;
+    if let Some(Command::ListEncodings) = &args.command {
+        print!("{}", format_encodings());
+        return Ok(());
+    }
+    if let Some(Command::Version) = &args.command {
+        println!("{}", format_version_info());
+        return Ok(());
+    }
+
+    if let Some(Command::Undo {
+        backup_dir,
+        overwrite_on_restore,
+    }) = &args.command
+    {
+        let manifest = backup_dir.join(BACKUP_MANIFEST_FILE_NAME);
+        if !manifest.is_file() {
+            eprintln!(
+                "Backup manifest is not found: {}. Abort.",
+                manifest.to_string_lossy()
+            );
+            std::process::exit(1);
         }
-        code += 1;
+        if let Some(sandbox) = &args.sandbox {
+            check_backup_sandbox(&manifest, backup_dir, sandbox)?;
+        }
+        let stats = restore_backup(&manifest, backup_dir, *overwrite_on_restore)
+            .context("restoring the backup")?;
+        println!(
+            "Restored: {}, Skipped: {}, Failed: {}",
+            stats.restored, stats.skipped, stats.failed
+        );
+        return Ok(());
     }
-    codes
-}
 
-/// Remove file.
-fn remove_file<P: AsRef<Path>>(path: P) {
-    let p = path.as_ref();
-    if p.is_dir() {
-        match fs::remove_dir(p) {
-            Ok(_) => {
-                println!("\tRemoved: {}.", p.to_string_lossy().into_owned());
+    if let Some(Command::Check {
+        archive,
+        show_unchanged,
+        crc_cache,
+    }) = &args.command
+    {
+        let options = SearchOptions {
+            normalize: !args.no_normalize,
+            verbose: args.verbose,
+            include_missing: true,
+            max_path_length: args.max_path_length,
+            ..Default::default()
+        };
+        let crc_cache_path = crc_cache.as_ref().map(|crc_cache_path| {
+            crc_cache_path.clone().unwrap_or_else(|| {
+                let mut file_name = archive.file_name().unwrap_or_default().to_os_string();
+                file_name.push(".rmext_crc_cache");
+                archive.with_file_name(file_name)
+            })
+        });
+        if let Some(sandbox) = &args.sandbox {
+            for path in [archive]
+                .into_iter()
+                .chain(&crc_cache_path)
+                .chain(&search_paths(archive, args.mode))
+            {
+                check_sandbox(path, sandbox)?;
             }
-            Err(e) => eprintln!(
-                "Failed to remove {}: {}",
-                p.to_string_lossy().into_owned(),
-                e
-            ),
         }
-    } else {
-        match fs::remove_file(p) {
-            Ok(_) => {
-                println!("\tRemoved: {}.", p.to_string_lossy().into_owned());
+        if let Some(archive_type) = args.archive_type {
+            warn_archive_type_mismatch(archive, archive_type);
+        }
+        let entries = read_file_entries(archive, &args.encoding, args.archive_type)
+            .with_context(|| format!("reading {}", archive.to_string_lossy()))?;
+        let mut crc_cache = match crc_cache_path {
+            Some(crc_cache_path) => Some(CrcCache::load(&crc_cache_path).with_context(|| {
+                format!("loading the CRC cache {}", crc_cache_path.to_string_lossy())
+            })?),
+            None => None,
+        };
+        for search_path in search_paths(archive, args.mode) {
+            let contents =
+                search_content_paths(entries.iter().cloned().map(Ok), [&search_path], &options)
+                    .context("searching archive contents")?;
+            let report = check_extraction(&contents, &search_path, crc_cache.as_mut());
+            println!("{}:", Path::new(".").join(&search_path).to_string_lossy());
+            for entry in &report.entries {
+                if *show_unchanged || entry.status != CheckStatus::Present {
+                    println!("\t{}", entry);
+                }
             }
-            Err(e) => eprintln!(
-                "Failed to remove {}: {}",
-                p.to_string_lossy().into_owned(),
-                e
-            ),
         }
+        if let Some(crc_cache) = &mut crc_cache {
+            crc_cache.save().context("saving the CRC cache")?;
+        }
+        return Ok(());
     }
-}
 
-/// Search path to delete.
-fn search_rar_content_path_to_delete<P: AsRef<Path>>(
-    zip_path: P,
-    encoding: &str,
-    search_path: P,
-) -> Vec<PathBuf> {
-    eprintln!("Not Implemented.");
-    std::process::exit(1);
-}
-
-/// Search path to delete.
-fn search_zip_content_path_to_delete<P: AsRef<Path>>(
-    headers: &Vec<CentralDirectoryFileHeader>,
-    search_path: P,
-) -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-    for header in headers {
-        let content_path = search_path
-            .as_ref()
-            .join(normalize_file_name(&header.file_name));
-        if content_path.exists()
-            && content_path.is_file()
-            && content_path.metadata().unwrap().len() == u64::from(header.uncompressed_size)
-        {
-            paths.push(content_path);
+    if let Some(sandbox) = &args.sandbox {
+        check_option_paths_sandbox(&args, sandbox)?;
+    }
+    let mut archive_paths: Vec<PathBuf> = args
+        .path
+        .iter()
+        .filter(|path| *path != "-")
+        .map(|path| expand_glob_path(path, args.glob_path))
+        .collect::<Result<Vec<_>>>()
+        .context("expanding --path")?
+        .into_iter()
+        .flatten()
+        .collect();
+    if let Some(path_file) = &args.path_file {
+        let paths = fs::read_to_string(path_file)
+            .with_context(|| format!("reading {}", path_file.to_string_lossy()))?;
+        for line in paths.lines() {
+            if !line.trim().is_empty() {
+                archive_paths.push(PathBuf::from(line.trim()));
+            }
+        }
+    }
+    let password_from_stdin = args.zip_password.as_deref() == Some("-");
+    if password_from_stdin {
+        let mut password = String::new();
+        io::stdin()
+            .read_line(&mut password)
+            .context("reading the zip password from stdin")?;
+        args.zip_password = Some(password.trim_end_matches(['\r', '\n']).to_string());
+    }
+    if archive_paths.is_empty() {
+        // Stdin is taken by the password.
+        let no_stdin = args.no_stdin
+            || std::env::var("RMEXT_NO_STDIN").is_ok_and(|v| v == "1")
+            || password_from_stdin;
+        if is_stdin(args.path.first(), no_stdin) {
+            archive_paths.extend(
+                read_from_stdin(args.stdin_path_delimiter)
+                    .context("reading archive paths from stdin")?
+                    .into_iter()
+                    .map(PathBuf::from),
+            );
+        } else {
+            // Print help.
+            let mut cmd = Args::command();
+            let _ = cmd.print_help();
+            std::process::exit(1);
         }
     }
-    paths
-}
 
-/// Normalize zip content file name.  
-/// e.g.) `../A/../A/./B.txt => A/A/B.txt`
-fn normalize_file_name(file_name: &str) -> String {
-    if cfg!(windows) {
-        file_name
-            .replace("/", "\\")
-            .replace("..\\", "")
-            .replace(".\\", "")
-    } else {
-        file_name
-            .replace("\\", "/")
-            .replace("../", "")
-            .replace("./", "")
+    let timeout = args.timeout_secs.map(Duration::from_secs);
+    if let Some(sandbox) = &args.sandbox {
+        for path in &archive_paths {
+            check_sandbox(path, sandbox)?;
+        }
     }
-}
 
-/// Sort path by depth
-fn sort_path_by_depth<P: AsRef<Path>>(paths: &mut Vec<P>) {
-    let separator = if cfg!(windows) { "\\" } else { "/" };
-    paths.sort_by(|a, b| {
-        b.as_ref()
-            .to_string_lossy()
-            .into_owned()
-            .matches(separator)
-            .count()
-            .cmp(
-                &a.as_ref()
-                    .to_string_lossy()
-                    .into_owned()
-                    .matches(separator)
-                    .count(),
-            )
-            .then(a.as_ref().cmp(&b.as_ref()))
+    if args.no_normalize {
+        eprintln!("Warning: --no-normalize disables path traversal protection.");
+    }
+    let options = SearchOptions {
+        normalize: !args.no_normalize,
+        verbose: args.verbose,
+        include_missing: args.only_empty_archives || args.list_all,
+        max_path_length: args.max_path_length,
+        match_strategy: match_strategy(&args),
+        preserve_dirs: args.preserve_dirs,
+        skip_hidden: args.skip_hidden,
+        ignore_artifact_dirs: args.ignore_dot_zip_artifacts,
+        prefix: args.prefix.as_deref().map(normalize_prefix),
+        match_renamed: args.match_renamed,
+    };
+
+    if args.dump_eocd {
+        for archive_path in &archive_paths {
+            println!("{}:", archive_path.to_string_lossy());
+            match ZipFileReader::new(archive_path, args.encoding.to_string()).and_then(
+                |mut reader| {
+                    reader.set_comment_encoding(args.archive_comment_encoding.clone());
+                    reader.dump_eocd()
+                },
+            ) {
+                Ok(dump) => println!("{}", dump),
+                Err(e) => eprintln!("Failed to read {}: {}", archive_path.to_string_lossy(), e),
+            }
+        }
+        return Ok(());
+    }
+
+    if args.verify_extraction_complete {
+        let mut incomplete = false;
+        for archive_path in &archive_paths {
+            let missing_entries = missing_archive_entries(archive_path, &args, &options)
+                .with_context(|| format!("reading {}", archive_path.to_string_lossy()))?;
+            if missing_entries.is_empty() {
+                continue;
+            }
+            incomplete = true;
+            println!(
+                "The following contents of {} are not found:",
+                archive_path_relative_to_cwd(archive_path).to_string_lossy()
+            );
+            for file_name in &missing_entries {
+                print_line(&format!("\t{}", file_name), &args.output_encoding);
+            }
+        }
+        if incomplete {
+            eprintln!("The extraction may be incomplete. Abort.");
+            std::process::exit(EXIT_EXTRACTION_INCOMPLETE);
+        }
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.parallel_archives)
+        .build()
+        .context("building the thread pool")?;
+    let results: Vec<Result<ArchiveSearch>> = pool.install(|| {
+        archive_paths
+            .par_iter()
+            .map(|archive_path| search_archive(archive_path, &args, &options))
+            .collect()
     });
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{normalize_file_name, unpack_mode};
+    let mut deletion_entries = Vec::new();
+    let mut encoding_reports = Vec::new();
+    let mut archive_infos = Vec::new();
+    let mut inconsistencies = Vec::new();
+    let mut errors = Vec::new();
+    for (archive_path, result) in archive_paths.iter().zip(results) {
+        match result {
+            Ok(search) => {
+                deletion_entries.extend(search.deletion_entries);
+                if let Some(report) = search.encoding_report {
+                    encoding_reports.push((archive_path, report));
+                }
+                if let Some(archive_info) = search.archive_info {
+                    archive_infos.push((archive_path, archive_info));
+                }
+                if let Some(report @ ValidationReport::Inconsistency(_)) = search.validation_report
+                {
+                    inconsistencies.push((archive_path, report));
+                }
+            }
+            Err(e) => errors.push((archive_path, e)),
+        }
+    }
+    for (archive_path, e) in &errors {
+        eprintln!(
+            "Failed to read {}: {}",
+            archive_path_relative_to_cwd(archive_path).to_string_lossy(),
+            e
+        );
+    }
+    if errors.len() == archive_paths.len() {
+        std::process::exit(1);
+    }
+    for (archive_path, (archive_info, origin_os)) in &archive_infos {
+        println!(
+            "Archive: {} | {} | Origin OS: {}",
+            archive_path_relative_to_cwd(archive_path).to_string_lossy(),
+            archive_info,
+            origin_os.as_deref().unwrap_or("--")
+        );
+    }
+    dedup_deletion_entries(&mut deletion_entries, !args.no_sort);
+    if !args.allow_delete_archive && exclude_archives(&mut deletion_entries, &archive_paths) > 0 {
+        eprintln!("WARNING: Archive file itself was in deletion list; skipping.");
+    }
+    if let Some(sandbox) = &args.sandbox {
+        for deletion_entry in &deletion_entries {
+            check_sandbox(&deletion_entry.path, sandbox)?;
+        }
+    }
 
-    #[test]
-    fn unpack_all_delete_mode() {
-        let mut codes = Vec::new();
-        codes.push(1);
-        codes.push(2);
-        assert_eq!(codes, unpack_mode(3));
+    if let Some(digest_file) = &args.digest_file {
+        let digests = parse_digest_file(digest_file)
+            .with_context(|| format!("reading {}", digest_file.to_string_lossy()))?;
+        deletion_entries.retain(|deletion_entry| {
+            let Some(digest) = std::path::absolute(&deletion_entry.path)
+                .ok()
+                .and_then(|path| digests.get(&path))
+            else {
+                return true;
+            };
+            let matched = sha256_file(&deletion_entry.path).is_ok_and(|hash| &hash == digest);
+            if !matched {
+                println!(
+                    "\t{} does not match the checksum. Skip removing.",
+                    deletion_entry.path.to_string_lossy()
+                );
+            }
+            matched
+        });
     }
 
-    #[test]
-    fn trim_dot_slash_in_path() {
-        assert_eq!("A/A/B.txt", normalize_file_name("../A/../A/./B.txt"));
+    let print_encoding_reports = || {
+        for (archive_path, report) in &encoding_reports {
+            if archive_paths.len() > 1 {
+                println!("{}:", archive_path.to_string_lossy());
+            }
+            report.print();
+        }
+    };
+
+    if args.total_size_only {
+        if deletion_entries.is_empty() {
+            std::process::exit(1);
+        }
+        let paths_to_delete: Vec<PathBuf> = deletion_entries
+            .iter()
+            .map(|deletion_entry| deletion_entry.path.clone())
+            .filter(|path| path.is_file())
+            .collect();
+        println!("{}", total_size(&paths_to_delete));
+        return Ok(());
+    }
+
+    if args.count_only {
+        println!("{}", deletion_entries.len());
+        if deletion_entries.is_empty() {
+            std::process::exit(EXIT_NOTHING_FOUND);
+        }
+        return Ok(());
+    }
+
+    if args.only_empty_archives {
+        println!("Skip removing archive contents.");
+        // No files are removed, only the empty directories.
+        let confirm_message = interpolate_confirm(&args.confirm_message, 0, 0);
+        if !args.list && !args.list_all && confirm(&confirm_message, args.interactive, timeout)? {
+            remove_empty_directories(
+                &*new_remover(args.simulate_readonly),
+                &deletion_entries,
+                args.max_depth,
+                !args.no_recursive_warn,
+                &mut io::stdout(),
+            )
+            .context("removing empty directories")?;
+        }
+        return Ok(());
+    }
+
+    if deletion_entries.is_empty() {
+        println!("Archive contents are not found.");
+        print_encoding_reports();
+        println!("Skip removing.");
+        return Ok(());
+    }
+
+    if args.directory_summary {
+        let entries: Vec<FileEntry> = deletion_entries
+            .iter()
+            .map(|deletion_entry| deletion_entry.entry.clone())
+            .collect();
+        let mut groups = Vec::from_iter(group_by_top_dir(&entries));
+        groups.sort();
+        println!("The following directories will be Removed:");
+        for (group, (count, size)) in groups {
+            println!("\t{} ({} files, {})", group, count, format_size(size));
+        }
+        print_encoding_reports();
+        println!("Skip removing.");
+        return Ok(());
+    }
+
+    if args.delete_archive {
+        let found_archive_paths: Vec<PathBuf> = deletion_entries
+            .iter()
+            .map(|deletion_entry| deletion_entry.archive_path.clone())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        for archive_path in found_archive_paths {
+            let metadata = fs::metadata(&archive_path).with_context(|| {
+                format!("reading metadata of {}", archive_path.to_string_lossy())
+            })?;
+            deletion_entries.push(DeletionEntry {
+                path: archive_path.clone(),
+                entry: FileEntry {
+                    file_name: archive_path.to_string_lossy().into_owned(),
+                    uncompressed_size: metadata.len(),
+                    crc32: None,
+                    last_modified: metadata.modified().ok(),
+                    is_directory: false,
+                    version_made_by: None,
+                    is_encrypted: false,
+                },
+                archive_path,
+            });
+        }
+        if !args.no_sort {
+            sort_deletion_entries(&mut deletion_entries);
+        }
+    }
+
+    if args.list_all {
+        // Found ones first, then not found ones, then ones with size mismatch.
+        deletion_entries.sort_by_key(list_status);
+    }
+
+    let listed_paths: Vec<PathBuf> = if args.list_duplicates {
+        deletion_entries
+            .iter()
+            .map(|deletion_entry| deletion_entry.path.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let duplicate_names = find_duplicate_names(&listed_paths);
+
+    let print_deletion_entry = |out: &mut dyn Write, deletion_entry: &DeletionEntry| {
+        let DeletionEntry {
+            path,
+            entry,
+            archive_path,
+        } = deletion_entry;
+        let display_path = archive_path_relative_to_cwd(path);
+        let mut delete_dir = match args.truncate_names {
+            Some(max_chars) => truncate_path_display(&display_path, max_chars),
+            None => display_path.to_string_lossy().into_owned(),
+        };
+        if path == archive_path {
+            delete_dir.push_str(" [ARCHIVE]");
+        }
+        if entry.is_encrypted {
+            delete_dir.push_str(" [ENCRYPTED]");
+        }
+        if args.match_renamed
+            && path != archive_path
+            && path.file_name() != Path::new(&convert_separator(&entry.file_name)).file_name()
+        {
+            delete_dir.push_str(&format!(" [RENAMED: {}]", entry.file_name));
+        }
+        if let Some(duplicates) = path
+            .file_name()
+            .and_then(|file_name| duplicate_names.get(file_name.to_string_lossy().as_ref()))
+        {
+            let others: Vec<String> = duplicates
+                .iter()
+                .filter(|duplicate| duplicate.parent() != path.parent())
+                .map(|duplicate| {
+                    archive_path_relative_to_cwd(duplicate)
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            delete_dir.push_str(&format!(" [DUPLICATE: also at {}]", others.join(", ")));
+        }
+        if args.list_all {
+            match list_status(deletion_entry) {
+                ListStatus::Found => {}
+                ListStatus::NotFound => delete_dir.push_str(" [NOT FOUND]"),
+                ListStatus::SizeMismatch(size) => {
+                    delete_dir.push_str(&format!(" [SIZE MISMATCH: disk={}]", size))
+                }
+            }
+        }
+        let mut columns = Vec::new();
+        if args.timestamps {
+            let timestamp = entry
+                .last_modified
+                .as_ref()
+                .map(format_timestamp)
+                .unwrap_or_else(|| "--".to_string());
+            columns.push(format!("{:<23}", timestamp));
+        }
+        if args.format_version {
+            let version = entry
+                .version_made_by
+                .map(|version_made_by| {
+                    let (os, version) = decode_version_made_by(version_made_by);
+                    format!("{} {}", os, version)
+                })
+                .unwrap_or_else(|| "--".to_string());
+            columns.push(format!("{:<16}", version));
+        }
+        columns.push(delete_dir);
+        write_line(
+            out,
+            &format!("\t{}", columns.join("  ")),
+            &args.output_encoding,
+        )
+    };
+
+    // The list is written to --output-file at once after it is complete.
+    let mut listing = Vec::new();
+    let mut stdout = io::stdout();
+    let out: &mut dyn Write = if args.output_file.is_some() {
+        &mut listing
+    } else {
+        &mut stdout
+    };
+    writeln!(out, "The following files will be Removed:")?;
+    if args.print_tree {
+        for archive_path in &archive_paths {
+            let paths: Vec<PathBuf> = deletion_entries
+                .iter()
+                .filter(|deletion_entry| &deletion_entry.archive_path == archive_path)
+                .map(|deletion_entry| deletion_entry.path.clone())
+                .collect();
+            if paths.is_empty() {
+                continue;
+            }
+            let tree = render_tree(&paths, archive_dir(archive_path));
+            for line in tree.lines() {
+                write_line(out, line, &args.output_encoding)?;
+            }
+        }
+    } else if args.group_by_archive {
+        for archive_path in &archive_paths {
+            let archive_entries: Vec<&DeletionEntry> = deletion_entries
+                .iter()
+                .filter(|deletion_entry| &deletion_entry.archive_path == archive_path)
+                .collect();
+            if archive_entries.is_empty() {
+                continue;
+            }
+            writeln!(
+                out,
+                "=== {} ===",
+                archive_path_relative_to_cwd(archive_path).to_string_lossy()
+            )?;
+            for deletion_entry in &archive_entries {
+                print_deletion_entry(out, deletion_entry)?;
+            }
+            writeln!(out, "\t{} files", archive_entries.len())?;
+        }
+        writeln!(out, "Total: {} files", deletion_entries.len())?;
+    } else {
+        for deletion_entry in &deletion_entries {
+            print_deletion_entry(out, deletion_entry)?;
+        }
+    }
+    if let Some(output_file) = args.output_file.as_ref().filter(|_| args.split_output) {
+        let paths: Vec<(PathBuf, bool)> = deletion_entries
+            .iter()
+            .map(|deletion_entry| {
+                (
+                    archive_path_relative_to_cwd(&deletion_entry.path),
+                    deletion_entry.entry.is_directory,
+                )
+            })
+            .collect();
+        let delimiter = if args.print0 { b'\0' } else { b'\n' };
+        let (files_path, dirs_path) =
+            write_split_output(output_file, &paths, delimiter, &args.output_encoding)
+                .with_context(|| {
+                    format!("writing the list to {}", output_file.to_string_lossy())
+                })?;
+        println!(
+            "The list of {} files is written to {} and {}.",
+            deletion_entries.len(),
+            files_path.to_string_lossy(),
+            dirs_path.to_string_lossy()
+        );
+    } else if let Some(output_file) = &args.output_file {
+        write_atomically(output_file, &listing)
+            .with_context(|| format!("writing the list to {}", output_file.to_string_lossy()))?;
+        println!(
+            "The list of {} files is written to {}.",
+            deletion_entries.len(),
+            output_file.to_string_lossy()
+        );
+    }
+
+    let write_hash_file = |deletion_entries: &[DeletionEntry]| {
+        let Some(hash_file) = &args.hash_file else {
+            return;
+        };
+        let paths: Vec<PathBuf> = deletion_entries
+            .iter()
+            .map(|deletion_entry| deletion_entry.path.clone())
+            .filter(|path| path.is_file())
+            .collect();
+        if let Err(e) = compute_and_write_hashes(&paths, hash_file) {
+            eprintln!(
+                "Failed to write hashes to {}: {}. Abort.",
+                hash_file.to_string_lossy(),
+                e
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if args.list || args.list_all {
+        if args.verbose {
+            let entries: Vec<FileEntry> = deletion_entries
+                .iter()
+                .filter(|deletion_entry| deletion_entry.path != deletion_entry.archive_path)
+                .map(|deletion_entry| deletion_entry.entry.clone())
+                .collect();
+            println!("Size histogram:");
+            println!("\t{}", format_histogram(&size_histogram(&entries)));
+        }
+        write_hash_file(&deletion_entries);
+        print_encoding_reports();
+        println!("Skip removing.");
+        return Ok(());
+    }
+
+    if !inconsistencies.is_empty() {
+        eprintln!("The following archives are not self-consistent:");
+        for (archive_path, report) in &inconsistencies {
+            eprintln!(
+                "\t{}: {}",
+                archive_path_relative_to_cwd(archive_path).to_string_lossy(),
+                report
+            );
+        }
+        eprintln!("Abort.");
+        std::process::exit(1);
+    }
+
+    if args.delete_archive {
+        println!("The following archives will also be Removed:");
+        for DeletionEntry {
+            path, archive_path, ..
+        } in &deletion_entries
+        {
+            if path == archive_path {
+                print_path(&archive_path_relative_to_cwd(path), &args.output_encoding);
+            }
+        }
+        if !confirm(
+            "Do you want to remove the archives? [Y/n] ",
+            args.interactive,
+            timeout,
+        )? {
+            println!("Skip removing the archives.");
+            deletion_entries
+                .retain(|deletion_entry| deletion_entry.path != deletion_entry.archive_path);
+        }
+    }
+
+    if args.check_permissions {
+        let paths_to_delete: Vec<PathBuf> = deletion_entries
+            .iter()
+            .map(|deletion_entry| deletion_entry.path.clone())
+            .collect();
+        let unwritable_paths = check_write_permissions(&paths_to_delete);
+        if !unwritable_paths.is_empty() {
+            eprintln!("The following files are not writable:");
+            for path in &unwritable_paths {
+                eprintln!("\t{}", path.to_string_lossy());
+            }
+            if args.strict {
+                eprintln!("Abort.");
+                std::process::exit(1);
+            }
+            if !confirm(
+                "Do you want to continue without them? [Y/n] ",
+                args.interactive,
+                timeout,
+            )? {
+                println!("Abort.");
+                return Ok(());
+            }
+            deletion_entries
+                .retain(|deletion_entry| !unwritable_paths.contains(&deletion_entry.path));
+        }
+    }
+
+    if args.interactive_editor {
+        let paths: Vec<PathBuf> = deletion_entries
+            .iter()
+            .map(|deletion_entry| deletion_entry.path.clone())
+            .collect();
+        let editor = std::env::var("EDITOR")
+            .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+        let Some(curated_paths) =
+            edit_deletion_list(&paths, &editor).context("editing the list of files")?
+        else {
+            println!("The list is not edited. Abort.");
+            return Ok(());
+        };
+        deletion_entries.retain(|deletion_entry| curated_paths.contains(&deletion_entry.path));
+    }
+
+    let plan = build_deletion_plan(
+        deletion_entries,
+        DeletionConfig {
+            backup_dir: args.backup_dir.clone(),
+            atomic_delete: args.atomic_delete,
+            recursive: args.recursive,
+            no_recursive_warn: args.no_recursive_warn,
+            max_depth: args.max_depth,
+            deleted_paths_log: args.log_deleted_paths_to.clone(),
+            print0: args.print0,
+            fail_fast: args.fail_fast,
+            max_errors: args.max_errors,
+            verbose: args.verbose,
+            simulate_readonly: args.simulate_readonly,
+        },
+    );
+
+    if let Some(size_threshold) = args.size_threshold {
+        if plan.stats.bytes < size_threshold {
+            println!(
+                "Matched files are below the size threshold (matched: {}). Skipping.",
+                format_size(plan.stats.bytes)
+            );
+            return Ok(());
+        }
+    }
+
+    if let Some(backup_dir) = &plan.config.backup_dir {
+        fs::create_dir_all(backup_dir).with_context(|| {
+            format!(
+                "creating the backup directory {}",
+                backup_dir.to_string_lossy()
+            )
+        })?;
+        if args.disk_free_check {
+            if let Err(e) = check_disk_space(plan.stats.bytes, backup_dir) {
+                eprintln!("Can not back up to {}: {}", backup_dir.to_string_lossy(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let confirm_message = interpolate_confirm(
+        &args.confirm_message,
+        plan.stats.files as usize,
+        plan.stats.bytes,
+    );
+    if confirm(&confirm_message, args.interactive, timeout)? {
+        write_hash_file(&plan.entries);
+        let max_reruns = if args.rerun_on_partial { MAX_RERUNS } else { 0 };
+        let stats = execute_plan_with_reruns(&plan, max_reruns).context("removing files")?;
+        println!(
+            "Removed {} of {} files ({}).",
+            stats.files,
+            plan.stats.files,
+            format_size(stats.bytes)
+        );
+        if args.ext_report {
+            print_extension_report(&stats.removed);
+        }
+        if stats.errors_before_abort.is_some() {
+            eprintln!("Abort.");
+            std::process::exit(EXIT_REMOVAL_ABORTED);
+        }
+        if let Some(completion_action) = &args.completion_action {
+            let command = interpolate_completion_action(completion_action, &stats, &archive_paths);
+            run_completion_action(&command);
+        }
+    } else {
+        println!("Abort.");
+    }
+    Ok(())
+}
+
+/// Print the removed files and freed bytes by the file extension, largest first.
+fn print_extension_report(removed: &[(PathBuf, u64)]) {
+    let mut groups = Vec::from_iter(extension_report(removed));
+    groups.sort_by(|(a, (_, a_bytes)), (b, (_, b_bytes))| b_bytes.cmp(a_bytes).then(a.cmp(b)));
+    println!("Extension | Files Removed | Bytes Freed | Avg Size");
+    for (extension, (count, bytes)) in groups {
+        println!(
+            "{} | {} | {} | {}",
+            extension,
+            count,
+            format_size(bytes),
+            format_size(bytes / count)
+        );
+    }
+}
+
+/// Build the plan to remove the files. Only the metadata of the files is read.
+fn build_deletion_plan(
+    deletion_entries: Vec<DeletionEntry>,
+    config: DeletionConfig,
+) -> DeletionPlan {
+    let paths_to_delete: Vec<PathBuf> = deletion_entries
+        .iter()
+        .map(|deletion_entry| deletion_entry.path.clone())
+        .collect();
+    DeletionPlan {
+        stats: RemovalStats {
+            files: deletion_entries.len() as u64,
+            bytes: total_size(&paths_to_delete),
+            ..Default::default()
+        },
+        entries: deletion_entries,
+        config,
+    }
+}
+
+/// Back up and remove the files of the plan. Returns the files actually removed.
+fn execute_plan(plan: &DeletionPlan) -> Result<RemovalStats> {
+    let DeletionPlan {
+        entries, config, ..
+    } = plan;
+    let mut deleted_paths_log = match &config.deleted_paths_log {
+        Some(path) => Some(BufWriter::new(
+            OpenOptions::new().create(true).append(true).open(path)?,
+        )),
+        None => None,
+    };
+    let delimiter = if config.print0 { b'\0' } else { b'\n' };
+    let mut log_deleted_path = |path: &Path| -> Result<()> {
+        if let Some(writer) = &mut deleted_paths_log {
+            writer.write_all(std::path::absolute(path)?.as_os_str().as_encoded_bytes())?;
+            writer.write_all(&[delimiter])?;
+        }
+        Ok(())
+    };
+    let mut removable_entries = Vec::new();
+    for deletion_entry in entries {
+        let Some(backup_dir) = &config.backup_dir else {
+            removable_entries.push((deletion_entry, None));
+            continue;
+        };
+        let DeletionEntry {
+            path, archive_path, ..
+        } = deletion_entry;
+        match backup_file(path, archive_dir(archive_path), backup_dir) {
+            Ok(record) => removable_entries.push((deletion_entry, Some(record))),
+            Err(e) => eprintln!(
+                "Failed to back up {}: {}. Skip removing.",
+                path.to_string_lossy(),
+                e
+            ),
+        }
+    }
+
+    let remover = new_remover(config.simulate_readonly);
+    let mut stats = RemovalStats::default();
+    let mut removed_records = Vec::new();
+    if config.atomic_delete {
+        let removable: Vec<&DeletionEntry> =
+            removable_entries.iter().map(|(entry, _)| *entry).collect();
+        let removed: Vec<(PathBuf, u64)> = removable
+            .iter()
+            .map(|deletion_entry| {
+                let path = deletion_entry.path.clone();
+                let size = total_size(std::slice::from_ref(&path));
+                (path, size)
+            })
+            .collect();
+        if let Err(e) = atomic_delete(&removable) {
+            // The files are kept, but record the backups already copied so that undo finds them.
+            if let Some(backup_dir) = &config.backup_dir {
+                for (_, record) in removable_entries {
+                    if let Some(record) = record {
+                        append_backup_record(&backup_dir.join(BACKUP_MANIFEST_FILE_NAME), &record)?;
+                    }
+                }
+            }
+            return Err(Error::new(
+                e.kind(),
+                format!(
+                    "failed to remove files atomically: {}. Nothing was removed",
+                    e
+                ),
+            ));
+        }
+        stats.files = removable.len() as u64;
+        stats.bytes = removed.iter().map(|(_, size)| size).sum();
+        for (path, _) in &removed {
+            log_deleted_path(path)?;
+        }
+        stats.removed = removed;
+        removed_records.extend(
+            removable_entries
+                .into_iter()
+                .filter_map(|(_, record)| record),
+        );
+    } else {
+        let max_errors = if config.fail_fast {
+            Some(1)
+        } else {
+            config.max_errors
+        };
+        // Archives are removed after their contents, and only if all of them were removed.
+        let (archives, contents): (Vec<_>, Vec<_>) = removable_entries
+            .into_iter()
+            .partition(|(deletion_entry, _)| is_archive_entry(deletion_entry));
+        let mut removable_entries = contents.into_iter().chain(archives);
+        for (deletion_entry, record) in removable_entries.by_ref() {
+            if is_archive_entry(deletion_entry)
+                && has_remaining_contents(entries, &deletion_entry.archive_path, &stats)
+            {
+                println!(
+                    "\tKeep {}: some of its contents were not removed.",
+                    archive_path_relative_to_cwd(&deletion_entry.path).to_string_lossy()
+                );
+                continue;
+            }
+            let size = total_size(std::slice::from_ref(&deletion_entry.path));
+            if remove_file(&*remover, &deletion_entry.path).is_ok() {
+                log_deleted_path(&deletion_entry.path)?;
+                stats.files += 1;
+                stats.bytes += size;
+                stats.removed.push((deletion_entry.path.clone(), size));
+                removed_records.extend(record);
+                continue;
+            }
+            stats.failed += 1;
+            if max_errors.is_some_and(|max_errors| stats.failed >= max_errors) {
+                if !config.fail_fast {
+                    eprintln!("Maximum error count exceeded; aborting.");
+                }
+                stats.errors_before_abort = Some(stats.failed);
+                break;
+            }
+        }
+        if config.verbose && stats.errors_before_abort.is_some() {
+            for (deletion_entry, _) in removable_entries {
+                println!(
+                    "\tNOT ATTEMPTED: {}",
+                    archive_path_relative_to_cwd(&deletion_entry.path).to_string_lossy()
+                );
+            }
+        }
+    }
+    if let Some(writer) = &mut deleted_paths_log {
+        writer.flush()?;
+    }
+    if let Some(backup_dir) = &config.backup_dir {
+        for record in &removed_records {
+            append_backup_record(&backup_dir.join(BACKUP_MANIFEST_FILE_NAME), record)?;
+        }
+    }
+
+    if config.recursive && stats.errors_before_abort.is_none() {
+        remove_empty_directories(
+            &*remover,
+            entries,
+            config.max_depth,
+            !config.no_recursive_warn,
+            &mut io::stdout(),
+        )?;
+    }
+    Ok(stats)
+}
+
+/// Whether the entry is the archive itself added by --delete-archive.
+fn is_archive_entry(deletion_entry: &DeletionEntry) -> bool {
+    deletion_entry.path == deletion_entry.archive_path
+}
+
+/// Whether any content of the archive is not removed yet.
+fn has_remaining_contents(
+    entries: &[DeletionEntry],
+    archive_path: &Path,
+    stats: &RemovalStats,
+) -> bool {
+    let removed: HashSet<&PathBuf> = stats.removed.iter().map(|(path, _)| path).collect();
+    entries.iter().any(|deletion_entry| {
+        deletion_entry.archive_path == archive_path
+            && !is_archive_entry(deletion_entry)
+            && !removed.contains(&deletion_entry.path)
+    })
+}
+
+/// Execute the plan, then plan and execute again up to `max_reruns` times
+/// for the files which failed to be removed. The stats of all runs are added up.
+fn execute_plan_with_reruns(plan: &DeletionPlan, max_reruns: usize) -> Result<RemovalStats> {
+    let mut stats = execute_plan(plan)?;
+    for _ in 0..max_reruns {
+        if stats.failed == 0 || stats.errors_before_abort.is_some() {
+            break;
+        }
+        let removed_paths: HashSet<PathBuf> =
+            stats.removed.iter().map(|(path, _)| path.clone()).collect();
+        let remaining_entries = plan
+            .entries
+            .iter()
+            .filter(|deletion_entry| !removed_paths.contains(&deletion_entry.path))
+            .cloned()
+            .collect();
+        let rerun_plan = build_deletion_plan(remaining_entries, plan.config.clone());
+        let rerun_stats = execute_plan(&rerun_plan)?;
+        stats.files += rerun_stats.files;
+        stats.bytes += rerun_stats.bytes;
+        stats.removed.extend(rerun_stats.removed);
+        // Only the failures of the last run are left.
+        stats.failed = rerun_stats.failed;
+        stats.errors_before_abort = rerun_stats.errors_before_abort;
+    }
+    Ok(stats)
+}
+
+/// Move files into a staging directory next to each archive, then remove the staging directories.
+/// If moving a file fails, the files already moved are moved back and nothing is removed.
+fn atomic_delete(deletion_entries: &[&DeletionEntry]) -> Result<()> {
+    let staging_dir_name = format!(
+        ".rmext_staging_{}_{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default()
+    );
+    let mut staging_dirs = BTreeMap::new();
+    let mut moved_paths = Vec::new();
+    let mut result = Ok(());
+    for DeletionEntry {
+        path, archive_path, ..
+    } in deletion_entries
+    {
+        let parent = archive_dir(archive_path);
+        let staging_dir = staging_dirs
+            .entry(parent.to_path_buf())
+            .or_insert_with(|| parent.join(&staging_dir_name));
+        let relative_path = path
+            .strip_prefix(parent)
+            .unwrap_or_else(|_| Path::new(path.file_name().unwrap_or_default()));
+        let staged_path = staging_dir.join(relative_path);
+        result = staged_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::rename(path, &staged_path))
+            .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.to_string_lossy(), e)));
+        if result.is_err() {
+            break;
+        }
+        moved_paths.push((path, staged_path));
+    }
+
+    if let Err(e) = result {
+        for (path, staged_path) in moved_paths.iter().rev() {
+            if let Err(e) = fs::rename(staged_path, path) {
+                eprintln!(
+                    "Failed to move {} back to {}: {}",
+                    staged_path.to_string_lossy(),
+                    path.to_string_lossy(),
+                    e
+                );
+            }
+        }
+        for staging_dir in staging_dirs.values() {
+            let _ = fs::remove_dir_all(staging_dir);
+        }
+        return Err(e);
+    }
+
+    for staging_dir in staging_dirs.values() {
+        if let Err(e) = fs::remove_dir_all(staging_dir) {
+            let retry_command = if cfg!(windows) {
+                "rmdir /s /q"
+            } else {
+                "rm -rf"
+            };
+            eprintln!(
+                "Failed to remove {}: {}. Retry with: {} \"{}\"",
+                staging_dir.to_string_lossy(),
+                e,
+                retry_command,
+                staging_dir.to_string_lossy()
+            );
+        }
+    }
+    for (path, _) in &moved_paths {
+        println!(
+            "\tRemoved: {}.",
+            archive_path_relative_to_cwd(path).to_string_lossy()
+        );
+    }
+    Ok(())
+}
+
+/// Remove directories which are empty after removing archive contents.
+/// Ancestors of each entry are removed up to the directory containing its archive
+/// or up to `max_depth` levels above the entry. Returns the number of directories removed.
+/// If `warn`, a message is written to `out` once the first directory is removed.
+fn remove_empty_directories(
+    remover: &dyn Remover,
+    deletion_entries: &[DeletionEntry],
+    max_depth: Option<usize>,
+    warn: bool,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    let mut ancestor_paths_to_delete = HashSet::new();
+    for DeletionEntry {
+        path, archive_path, ..
+    } in deletion_entries
+    {
+        let parent = archive_dir(archive_path);
+        for (depth, ancestor) in path.ancestors().enumerate() {
+            if max_depth.is_some_and(|max_depth| max_depth < depth) {
+                break;
+            }
+            if ancestor_paths_to_delete.contains(&ancestor) || parent == ancestor {
+                break;
+            }
+            if ancestor.is_dir() {
+                ancestor_paths_to_delete.insert(ancestor);
+            }
+        }
+    }
+
+    let mut ancestor_paths_to_delete_sort_by_depth = Vec::from_iter(ancestor_paths_to_delete);
+    sort_path_by_depth(&mut ancestor_paths_to_delete_sort_by_depth);
+
+    let mut removed = 0;
+    for path in ancestor_paths_to_delete_sort_by_depth {
+        if !remover.is_empty_dir(path)? {
+            writeln!(
+                out,
+                "\t{} is not empty. Skip removing.",
+                path.to_string_lossy()
+            )?;
+            continue;
+        }
+        match remover.remove_dir(path) {
+            Ok(_) => {
+                if warn && removed == 0 {
+                    writeln!(out, "Remove empty directory recursively.")?;
+                }
+                writeln!(
+                    out,
+                    "\tRemoved: {}.",
+                    archive_path_relative_to_cwd(path).to_string_lossy()
+                )?;
+                removed += 1;
+            }
+            Err(e) => eprintln!("Failed to remove {}: {}", path.to_string_lossy(), e),
+        }
+    }
+    Ok(removed)
+}
+
+/// Let the user edit the list of paths with the editor.
+/// Lines starting with `#` and empty lines are ignored.
+/// Returns `None` if the editor fails or the list is unchanged.
+fn edit_deletion_list(paths: &[PathBuf], editor: &str) -> Result<Option<HashSet<PathBuf>>> {
+    let list_path = std::env::temp_dir().join(format!("rmext_edit_{}.txt", std::process::id()));
+    let mut content =
+        String::from("# Delete the lines of files to keep. Lines starting with # are ignored.\n");
+    for path in paths {
+        content.push_str(&path.to_string_lossy());
+        content.push('\n');
+    }
+    fs::write(&list_path, &content)?;
+
+    // EDITOR may include arguments, e.g.) `code --wait`.
+    let mut command = editor.split_whitespace();
+    let status = std::process::Command::new(command.next().unwrap_or_default())
+        .args(command)
+        .arg(&list_path)
+        .status();
+    let edited = fs::read_to_string(&list_path);
+    let _ = fs::remove_file(&list_path);
+    if !status?.success() {
+        return Ok(None);
+    }
+    let edited = edited?;
+    if edited == content {
+        return Ok(None);
+    }
+    Ok(Some(
+        edited
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(PathBuf::from)
+            .collect(),
+    ))
+}
+
+/// Replace `%n` with the number of files and `%s` with the total size in the confirmation prompt.
+/// e.g.) `Delete %n files (%s)? => Delete 3 files (1.2 MiB)?`
+fn interpolate_confirm(template: &str, count: usize, size: u64) -> String {
+    template
+        .replace("%n", &count.to_string())
+        .replace("%s", &format_size(size))
+}
+
+/// Replace `%n` with the number of removed files, `%s` with the removed bytes
+/// and `%a` with the archive paths separated by spaces in the completion action.
+/// Each archive path is quoted since it may contain spaces or shell syntax.
+fn interpolate_completion_action(
+    template: &str,
+    stats: &RemovalStats,
+    archive_paths: &[PathBuf],
+) -> String {
+    let archive_paths = archive_paths
+        .iter()
+        .map(|archive_path| shell_quote(&archive_path.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    template
+        .replace("%n", &stats.files.to_string())
+        .replace("%s", &stats.bytes.to_string())
+        .replace("%a", &archive_paths)
+}
+
+/// Quote `s` as a single word for the shell running the completion action.
+fn shell_quote(s: &str) -> String {
+    if cfg!(windows) {
+        // File names on Windows can not contain `"`.
+        format!("\"{}\"", s)
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// Run the completion action in the shell. Failures are only warned.
+fn run_completion_action(command: &str) {
+    let status = if cfg!(windows) {
+        std::process::Command::new("cmd")
+            .args(["/C", command])
+            .status()
+    } else {
+        std::process::Command::new("sh")
+            .args(["-c", command])
+            .status()
+    };
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warning: the completion action exited with {}.", status),
+        Err(e) => eprintln!("Warning: failed to run the completion action: {}", e),
+    }
+}
+
+/// Ask the user for confirmation. Answer yes automatically unless interactive.
+/// With `timeout`, abort with exit code 5 if no line is entered in time.
+fn confirm(message: &str, interactive: bool, timeout: Option<Duration>) -> io::Result<bool> {
+    print!("{}", message);
+    std::io::stdout().flush()?;
+
+    let mut buffer;
+    if interactive {
+        buffer = String::new();
+        if let Some(timeout) = timeout {
+            let Some(line) = read_line_with_timeout(io::BufReader::new(io::stdin()), timeout)
+            else {
+                eprintln!();
+                eprintln!("Timed out. Aborting.");
+                std::process::exit(EXIT_USER_ABORTED);
+            };
+            buffer = line;
+        } else {
+            io::stdin().read_line(&mut buffer)?;
+        }
+    } else {
+        buffer = String::from("y");
+        println!("{}", buffer);
+    }
+    Ok(buffer.trim().to_lowercase() == "y")
+}
+
+/// Read a line in another thread and wait for it until `timeout`.
+/// The remaining seconds are shown after the cursor on stderr if it is a terminal.
+/// Returns `None` on timeout.
+fn read_line_with_timeout<R: BufRead + Send + 'static>(
+    mut reader: R,
+    timeout: Duration,
+) -> Option<String> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        let result = reader.read_line(&mut line).map(|_| line);
+        let _ = sender.send(result);
+    });
+    let show_countdown = atty::is(Stream::Stderr);
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+        if show_countdown {
+            // Save the cursor, print after it and restore it so that the input is not disturbed.
+            eprint!(
+                "\x1b[s [{}s remaining]\x1b[u",
+                remaining.as_secs_f64().ceil()
+            );
+        }
+        match receiver.recv_timeout(remaining.min(Duration::from_secs(1))) {
+            Ok(result) => return Some(result.expect("Failed to read line")),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+/// Search archive contents extracted on disk.
+fn search_archive(
+    archive_path: &Path,
+    args: &Args,
+    options: &SearchOptions,
+) -> Result<ArchiveSearch> {
+    if let Some(archive_type) = args.archive_type {
+        warn_archive_type_mismatch(archive_path, archive_type);
+    }
+    let mut search_paths = search_paths(archive_path, args.mode);
+    if args.guess_stem && args.mode != Mode::Files && archive_stem(archive_path).is_some() {
+        // The directory of mode 2 comes last.
+        let stem_dir = search_paths.pop().unwrap();
+        let guessed_dir = guess_extraction_dir(archive_path, &stem_dir, args, options)?;
+        if guessed_dir != stem_dir {
+            eprintln!(
+                "Guessed extraction directory: {}",
+                archive_path_relative_to_cwd(&guessed_dir).to_string_lossy()
+            );
+        }
+        search_paths.push(guessed_dir);
+    }
+    search_archive_in(archive_path, &search_paths, args, options)
+}
+
+/// Candidate directory of mode 2 which matches the most archive contents.
+/// `stem_dir` is kept if no candidate matches more.
+fn guess_extraction_dir(
+    archive_path: &Path,
+    stem_dir: &Path,
+    args: &Args,
+    options: &SearchOptions,
+) -> Result<PathBuf> {
+    let options = SearchOptions {
+        verbose: false,
+        include_missing: false,
+        ..options.clone()
+    };
+    let parent = stem_dir.parent().unwrap_or_else(|| Path::new(""));
+    let stem = archive_stem(archive_path)
+        .unwrap_or_default()
+        .to_string_lossy();
+    let mut guessed = (stem_dir.to_path_buf(), 0);
+    for candidate in guess_stem_candidates(&stem) {
+        let dir = parent.join(candidate);
+        if !dir.is_dir() {
+            continue;
+        }
+        let count = search_archive_in(archive_path, std::slice::from_ref(&dir), args, &options)?
+            .deletion_entries
+            .len();
+        if count > guessed.1 {
+            guessed = (dir, count);
+        }
+    }
+    Ok(guessed.0)
+}
+
+/// Directory names an archive may have been extracted into, the bare stem first.
+/// e.g.) `Archive_1.0.2` => `Archive_1.0.2`, `archive_1.0.2`, `Archive`, `Archive_1`
+fn guess_stem_candidates(stem: &str) -> Vec<String> {
+    let lowercase = stem.to_lowercase();
+    let candidates = [
+        Some(stem),
+        Some(lowercase.as_str()),
+        strip_version_suffix(stem),
+        stem.split('.').next(),
+    ];
+    let mut guessed: Vec<String> = Vec::new();
+    for candidate in candidates.into_iter().flatten() {
+        if !candidate.is_empty() && !guessed.iter().any(|guessed| guessed == candidate) {
+            guessed.push(candidate.to_string());
+        }
+    }
+    guessed
+}
+
+/// Strip a version suffix matching `[_-][0-9]+(\.[0-9]+)*$`.
+fn strip_version_suffix(stem: &str) -> Option<&str> {
+    let mut rest = stem;
+    loop {
+        let head = rest.trim_end_matches(|c: char| c.is_ascii_digit());
+        if head.len() == rest.len() {
+            return None;
+        }
+        match head.strip_suffix('.') {
+            Some(head) => rest = head,
+            None => return head.strip_suffix(['_', '-']),
+        }
+    }
+}
+
+/// Search archive contents extracted under `search_paths`.
+fn search_archive_in(
+    archive_path: &Path,
+    search_paths: &[PathBuf],
+    args: &Args,
+    options: &SearchOptions,
+) -> Result<ArchiveSearch> {
+    let mut encoding_report = None;
+    let mut archive_info = None;
+    let mut validation_report = None;
+    let matched_entries = match args.archive_type.or_else(|| archive_format(archive_path)) {
+        Some(ArchiveFormat::Lha | ArchiveFormat::Rar) => {
+            let entries = read_file_entries(archive_path, &args.encoding, args.archive_type)?;
+            search_content_paths(entries.into_iter().map(Ok), search_paths, options)?
+        }
+        Some(ArchiveFormat::Zip) => {
+            let mut encoding = args.encoding.to_string();
+            if args.encoding_fallback {
+                if let Some(probed_encoding) =
+                    ZipFileReader::probe_encoding(archive_path, args.encoding_probe_n)?
+                {
+                    encoding = probed_encoding.to_string();
+                }
+            }
+            if options.verbose {
+                println!("Encoding: {}", encoding);
+            }
+            let mut reader = ZipFileReader::new(archive_path, encoding)?;
+            reader.set_force_encoding(args.force_encoding.clone());
+            reader.set_comment_encoding(args.archive_comment_encoding.clone());
+            if options.verbose {
+                reader.seek_end_of_central_directory_record()?;
+                for header in reader.entries()? {
+                    println!("\t{}", header?);
+                }
+            }
+            let top_dir = if args.strip_top_dir {
+                reader.seek_end_of_central_directory_record()?;
+                detect_and_strip_top_dir(&reader.read_central_directory_file_header()?)
+            } else {
+                None
+            };
+            // The top directory is stripped only for the directory containing the archive.
+            let (stripped_search_paths, search_paths): (Vec<&PathBuf>, Vec<&PathBuf>) =
+                search_paths.iter().partition(|search_path| {
+                    top_dir.is_some() && search_path.as_path() == archive_dir(archive_path)
+                });
+            let mut matched_entries = Vec::new();
+            let mut warned_encrypted_entries = HashSet::new();
+            for (prefix, search_paths) in [
+                (top_dir.as_deref(), stripped_search_paths),
+                (None, search_paths),
+            ] {
+                if search_paths.is_empty() {
+                    continue;
+                }
+                reader.seek_end_of_central_directory_record()?;
+                let entries = reader
+                    .entries()?
+                    .filter(|header| {
+                        !(args.ignore_symlinks_in_archive
+                            && header.as_ref().is_ok_and(is_symlink_entry))
+                    })
+                    .filter(|header| match header {
+                        Ok(header)
+                            if ZipFileReader::is_encrypted(header.general_purpose_bit_flag) =>
+                        {
+                            // The contents are not decrypted, so their CRC can not be verified.
+                            let skipped = args.zip_password.is_none()
+                                || options.match_strategy.uses_crc();
+                            if warned_encrypted_entries.insert(header.file_name.clone()) {
+                                if skipped {
+                                    eprintln!(
+                                        "ENCRYPTED: {} — skipped because its CRC can not be verified",
+                                        header.file_name
+                                    );
+                                } else {
+                                    eprintln!(
+                                        "ENCRYPTED: {} — matched without verifying CRC",
+                                        header.file_name
+                                    );
+                                }
+                            }
+                            !skipped
+                        }
+                        _ => true,
+                    })
+                    .map(|header| {
+                        header.map(|header| {
+                            let mut entry = FileEntry::from(&header);
+                            if let Some(file_name) =
+                                prefix.and_then(|prefix| entry.file_name.strip_prefix(prefix))
+                            {
+                                entry.file_name = file_name.to_string();
+                            }
+                            entry
+                        })
+                    });
+                matched_entries.extend(search_content_paths(entries, search_paths, options)?);
+            }
+            if args.encoding_report {
+                reader.seek_end_of_central_directory_record()?;
+                encoding_report = Some(EncodingReport::new(reader.entries()?));
+            }
+            if args.print_archive_info {
+                reader.seek_end_of_central_directory_record()?;
+                let origin_os = archive_origin_os(&reader.read_central_directory_file_header()?);
+                archive_info = reader
+                    .end_of_central_directory()
+                    .cloned()
+                    .map(|end_of_central_directory| (end_of_central_directory, origin_os));
+            }
+            if args.check_zip_before_delete {
+                validation_report = Some(validate_zip_consistency(&mut reader)?);
+            }
+            matched_entries
+        }
+        None => {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("unsupported file type: {}", archive_path.to_string_lossy()),
+            ))
+        }
+    };
+    Ok(ArchiveSearch {
+        deletion_entries: matched_entries
+            .into_iter()
+            .map(|(path, entry)| DeletionEntry {
+                path,
+                entry,
+                archive_path: archive_path.to_path_buf(),
+            })
+            .collect(),
+        encoding_report,
+        archive_info,
+        validation_report,
+    })
+}
+
+/// Names of the file entries of the archive which exist in none of the search paths.
+fn missing_archive_entries(
+    archive_path: &Path,
+    args: &Args,
+    options: &SearchOptions,
+) -> Result<Vec<String>> {
+    let options = SearchOptions {
+        verbose: false,
+        include_missing: true,
+        ..options.clone()
+    };
+    let search = search_archive(archive_path, args, &options)?;
+    let mut found = HashSet::new();
+    let mut file_names = Vec::new();
+    for DeletionEntry { path, entry, .. } in &search.deletion_entries {
+        if entry.is_directory {
+            continue;
+        }
+        if path.symlink_metadata().is_ok() {
+            found.insert(&entry.file_name);
+        } else if !file_names.contains(&entry.file_name) {
+            file_names.push(entry.file_name.clone());
+        }
+    }
+    file_names.retain(|file_name| !found.contains(file_name));
+    Ok(file_names)
+}
+
+/// Expand glob pattern in path.
+/// Without `force`, the pattern is expanded only if the path does not exist,
+/// since shells other than cmd.exe expand it before.
+fn expand_glob_path(path: &str, force: bool) -> Result<Vec<PathBuf>> {
+    let is_pattern = path.contains(['*', '?']);
+    if !is_pattern || !force && Path::new(path).exists() {
+        return Ok(vec![PathBuf::from(path)]);
+    }
+    if !force {
+        eprintln!(
+            "Warning: {} does not exist. Expand it as a glob pattern.",
+            path
+        );
+    }
+    let paths = glob::glob(path)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(io::Error::from)?;
+    Ok(paths)
+}
+
+/// Read archive contents regardless of the format.
+fn read_file_entries(
+    archive_path: &Path,
+    encoding: &str,
+    archive_type: Option<ArchiveFormat>,
+) -> Result<Vec<FileEntry>> {
+    Ok(open_archive(archive_path, encoding, archive_type)?.file_entries()?)
+}
+
+/// Warn if the signature of the archive does not look like the format forced by --archive-type.
+fn warn_archive_type_mismatch(archive_path: &Path, archive_type: ArchiveFormat) {
+    match detect_format(archive_path) {
+        Ok(Some(detected)) if detected != archive_type => eprintln!(
+            "WARNING: {} looks like {} but is read as {}.",
+            archive_path.to_string_lossy(),
+            detected,
+            archive_type
+        ),
+        Ok(None) => eprintln!(
+            "WARNING: {} does not start with a {} signature but is read as {}.",
+            archive_path.to_string_lossy(),
+            archive_type,
+            archive_type
+        ),
+        _ => {}
+    }
+}
+
+/// Detect stdin. Always false if `no_stdin_flag`.
+fn is_stdin(input: Option<&String>, no_stdin_flag: bool) -> bool {
+    if no_stdin_flag {
+        return false;
+    }
+    let is_request = matches!(input, Some(i) if i == "-");
+    let is_pipe = !atty::is(Stream::Stdin);
+    is_request || is_pipe
+}
+
+/// Read paths separated by `delimiter` from stdin.
+fn read_from_stdin(delimiter: char) -> Result<Vec<String>> {
+    let mut buf = String::new();
+    let stdin = io::stdin();
+    let mut handle = stdin.lock();
+    handle.read_to_string(&mut buf)?;
+    Ok(split_paths(&buf, delimiter))
+}
+
+/// Split paths by `delimiter`, trimming whitespace and skipping empty ones.
+fn split_paths(input: &str, delimiter: char) -> Vec<String> {
+    input
+        .split(delimiter)
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
+/// Unpack mode to codes in ascending order. The code `n` is yielded for each `n`-th bit set in mode.
+fn unpack_mode(mode: Mode) -> impl Iterator<Item = u32> {
+    let mode = mode as u8;
+    (0..u8::BITS)
+        .filter(move |&n| mode & (1 << n) != 0)
+        .map(|n| n + 1)
+}
+
+/// Directories to search archive contents for the mode.
+/// Mode 2 is skipped with a warning if the archive file name has no stem.
+fn search_paths(archive_path: &Path, mode: Mode) -> Vec<PathBuf> {
+    unpack_mode(mode)
+        .filter_map(|code| match code {
+            1 => Some(archive_dir(archive_path).to_path_buf()),
+            2 => match archive_stem(archive_path) {
+                Some(stem) => Some(archive_dir(archive_path).join(stem)),
+                None => {
+                    eprintln!(
+                        "WARNING: {} has no file stem. Skip searching the directory of mode 2.",
+                        archive_path.to_string_lossy()
+                    );
+                    None
+                }
+            },
+            _ => unreachable!("invalid mode code: {}", code),
+        })
+        .collect()
+}
+
+/// Total size of files in bytes.
+fn total_size(paths: &[PathBuf]) -> u64 {
+    paths
+        .iter()
+        .filter_map(|path| path.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Copy file into the backup directory keeping the path relative to `base`.
+fn backup_file(path: &Path, base: &Path, backup_dir: &Path) -> Result<BackupRecord> {
+    let relative_path = path
+        .strip_prefix(base)
+        .unwrap_or_else(|_| Path::new(path.file_name().unwrap_or_default()));
+    let backup_path = backup_dir.join(relative_path);
+    if let Some(backup_parent) = backup_path.parent() {
+        fs::create_dir_all(backup_parent)?;
+    }
+    fs::copy(path, &backup_path)?;
+    Ok(BackupRecord {
+        original: std::path::absolute(path)?,
+        backup: relative_path.to_path_buf(),
+    })
+}
+
+/// Replace aliases of encodings with their names, e.g.) `sjis => cp932`.
+/// Done before any subcommand reads the encodings.
+fn canonicalize_encodings(args: &mut Args) {
+    for encoding in [
+        Some(&mut args.encoding),
+        Some(&mut args.output_encoding),
+        args.force_encoding.as_mut(),
+        args.archive_comment_encoding.as_mut(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if let Some(canonical) = canonical_encoding(encoding) {
+            *encoding = canonical.to_string();
+        }
+    }
+}
+
+/// Reject encodings that are not supported, before any subcommand reads them.
+fn validate_encodings(args: &Args) -> anyhow::Result<()> {
+    for encoding in [
+        Some(&args.encoding),
+        Some(&args.output_encoding),
+        args.force_encoding.as_ref(),
+        args.archive_comment_encoding.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        anyhow::ensure!(
+            ALLOWED_ENCODINGS.contains(&encoding.to_lowercase().as_ref()),
+            "unsupported encoding: {} (see `rmext list-encodings`)",
+            encoding
+        );
+    }
+    Ok(())
+}
+
+/// Check that the files given by options are in `sandbox` before any of them is read or written.
+fn check_option_paths_sandbox(
+    args: &Args,
+    sandbox: &Path,
+) -> std::result::Result<(), SandboxViolation> {
+    for path in [
+        &args.path_file,
+        &args.backup_dir,
+        &args.output_file,
+        &args.hash_file,
+        &args.digest_file,
+        &args.log_deleted_paths_to,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        check_sandbox(path, sandbox)?;
+    }
+    Ok(())
+}
+
+/// Check that the backup directory, the backups and their original locations are in `sandbox`.
+fn check_backup_sandbox(manifest: &Path, backup_dir: &Path, sandbox: &Path) -> anyhow::Result<()> {
+    check_sandbox(backup_dir, sandbox)?;
+    for record in read_backup_records(manifest).context("reading the backup manifest")? {
+        check_sandbox(&backup_dir.join(&record.backup), sandbox)?;
+        check_sandbox(&record.original, sandbox)?;
+    }
+    Ok(())
+}
+
+/// Remove file.
+fn remove_file<P: AsRef<Path>>(remover: &dyn Remover, path: P) -> Result<()> {
+    let p = path.as_ref();
+    let result = if p.is_dir() {
+        remover.remove_dir(p)
+    } else {
+        remover.remove_file(p)
+    };
+    match &result {
+        Ok(_) => {
+            println!(
+                "\tRemoved: {}.",
+                archive_path_relative_to_cwd(p).to_string_lossy()
+            );
+        }
+        Err(e) => eprintln!(
+            "Failed to remove {}: {}",
+            p.to_string_lossy().into_owned(),
+            e
+        ),
+    }
+    result
+}
+
+/// Collect paths which can not be removed because of missing write permission.
+fn check_write_permissions(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let is_readonly = |path: &Path| {
+        fs::metadata(path)
+            .map(|metadata| metadata.permissions().readonly())
+            .unwrap_or(true)
+    };
+    paths
+        .iter()
+        .filter(|path| {
+            // On Unix, removing a file requires write permission on its directory.
+            is_readonly(path) || (cfg!(unix) && path.parent().is_some_and(is_readonly))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Search paths to delete under each of `search_paths` in one pass over `entries`.
+/// Matches are grouped by search path in the given order.
+fn search_content_paths<P: AsRef<Path>>(
+    entries: impl Iterator<Item = Result<FileEntry>>,
+    search_paths: impl IntoIterator<Item = P>,
+    options: &SearchOptions,
+) -> Result<Vec<(PathBuf, FileEntry)>> {
+    let search_paths: Vec<PathBuf> = search_paths
+        .into_iter()
+        .map(|search_path| match &options.prefix {
+            Some(prefix) => search_path.as_ref().join(prefix),
+            None => search_path.as_ref().to_path_buf(),
+        })
+        .collect();
+    let mut paths = vec![Vec::new(); search_paths.len()];
+    for entry in entries {
+        let entry = entry?;
+        // Directory entries are handled by the recursive empty directory removal.
+        if entry.is_directory && !options.include_missing {
+            continue;
+        }
+        let raw_file_name = convert_separator(&entry.file_name);
+        let normalized_file_name = normalize_file_name_lossy(&entry.file_name);
+        if options.verbose && raw_file_name != normalized_file_name {
+            println!("\t{} => {}", raw_file_name, normalized_file_name);
+        }
+        if options.skip_hidden && is_hidden_path(Path::new(&*normalized_file_name)) {
+            if options.verbose {
+                println!("\tHIDDEN_SKIPPED: {}", normalized_file_name);
+            }
+            continue;
+        }
+        if options.ignore_artifact_dirs
+            && is_within_ignored_dir(
+                Path::new(&*normalized_file_name),
+                IGNORED_ARTIFACT_DIR_NAMES,
+            )
+        {
+            if options.verbose {
+                println!("\tARTIFACT_SKIPPED: {}", normalized_file_name);
+            }
+            continue;
+        }
+        let file_name = if options.normalize {
+            &*normalized_file_name
+        } else {
+            raw_file_name.as_str()
+        };
+        for (search_path, paths) in search_paths.iter().zip(&mut paths) {
+            if let Some(path) = match_content_path(&entry, search_path.join(file_name), options) {
+                paths.push((path, entry.clone()));
+            }
+        }
+    }
+    Ok(paths.into_iter().flatten().collect())
+}
+
+/// Path on disk to delete for the archive entry expected at `content_path`, if any.
+fn match_content_path(
+    entry: &FileEntry,
+    content_path: PathBuf,
+    options: &SearchOptions,
+) -> Option<PathBuf> {
+    if options.preserve_dirs && (entry.is_directory || content_path.is_dir()) {
+        return None;
+    }
+    if content_path.to_string_lossy().len() > options.max_path_length {
+        if options.verbose {
+            println!("\tLONG_PATH_SKIPPED: {}", content_path.to_string_lossy());
+        }
+        return None;
+    }
+    if options.include_missing {
+        return Some(content_path);
+    }
+    let strategy = match entry.crc32 {
+        None if options.match_strategy.uses_crc() => MatchStrategy::NameAndSize,
+        _ => options.match_strategy,
+    };
+    if let Some(crc32) = entry.crc32.filter(|_| {
+        strategy == MatchStrategy::CrcOnly && options.match_renamed && !entry.is_directory
+    }) {
+        // A bare name has an empty parent, and a root has none.
+        let dir = content_path.parent().unwrap_or(Path::new(""));
+        let name = content_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        find_file_by_crc(crc32, dir, &name)
+    } else if matches(entry, &content_path, strategy) {
+        Some(content_path)
+    } else {
+        None
+    }
+}
+
+/// Whether the file at `candidate` is the archive entry by `strategy`.
+/// The name is already matched by `candidate`.
+fn matches(entry: &FileEntry, candidate: &Path, strategy: MatchStrategy) -> bool {
+    if !candidate.is_file() {
+        return false;
+    }
+    let size_matches = || {
+        candidate
+            .metadata()
+            .is_ok_and(|metadata| metadata.len() == entry.uncompressed_size)
+    };
+    let crc_matches = || {
+        entry
+            .crc32
+            .is_some_and(|crc32| crc32_file(candidate).ok() == Some(crc32))
+    };
+    match strategy {
+        MatchStrategy::NameOnly => true,
+        MatchStrategy::NameAndSize => size_matches(),
+        MatchStrategy::CrcOnly | MatchStrategy::NameAndCrc => crc_matches(),
+        MatchStrategy::NameSizeAndCrc => size_matches() && crc_matches(),
+        MatchStrategy::NameAndSizeOrCrc => size_matches() || crc_matches(),
+    }
+}
+
+/// Normalize zip content file name without allocating if nothing is changed.  
+/// e.g.) `../A/../A/./B.txt => A/A/B.txt`
+fn normalize_file_name_lossy(file_name: &str) -> Cow<'_, str> {
+    let file_name = strip_extended_length_prefix(file_name);
+    let (foreign_separator, current_dir) = if cfg!(windows) {
+        ('/', ".\\")
+    } else {
+        ('\\', "./")
+    };
+    // `../` also contains `./`.
+    if !file_name.contains(foreign_separator) && !file_name.contains(current_dir) {
+        return Cow::Borrowed(file_name);
+    }
+    let normalized = if cfg!(windows) {
+        convert_separator(file_name)
+            .replace("..\\", "")
+            .replace(".\\", "")
+    } else {
+        convert_separator(file_name)
+            .replace("../", "")
+            .replace("./", "")
+    };
+    Cow::Owned(normalized)
+}
+
+/// Normalize the path given to --prefix, which must stay relative to the search path.
+/// Only normal components are kept, e.g.) `/../extracted/ => extracted`, `a/.. => a`
+fn normalize_prefix(prefix: &str) -> String {
+    Path::new(&convert_separator(prefix))
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Strip the Windows extended-length path prefix with the drive or the server and share.
+/// e.g.) `\\?\C:\A\B.txt => A\B.txt`, `\\?\UNC\server\share\A\B.txt => A\B.txt`
+fn strip_extended_length_prefix(file_name: &str) -> &str {
+    if let Some(rest) = file_name.strip_prefix(r"\\?\UNC\") {
+        return rest.splitn(3, '\\').nth(2).unwrap_or_default();
+    }
+    let Some(rest) = file_name.strip_prefix(r"\\?\") else {
+        return file_name;
+    };
+    match rest.as_bytes() {
+        [drive, b':', ..] if drive.is_ascii_alphabetic() => rest[2..].trim_start_matches('\\'),
+        _ => rest,
+    }
+}
+
+/// Convert path separators in zip content file name to the platform ones.
+fn convert_separator(file_name: &str) -> String {
+    if cfg!(windows) {
+        file_name.replace('/', "\\")
+    } else {
+        file_name.replace('\\', "/")
+    }
+}
+
+/// Remove entries which are the archives themselves, e.g.) an entry named after the archive.
+/// Returns the number of removed entries.
+fn exclude_archives(deletion_entries: &mut Vec<DeletionEntry>, archive_paths: &[PathBuf]) -> usize {
+    let archives: HashSet<PathBuf> = archive_paths
+        .iter()
+        .filter_map(|archive_path| fs::canonicalize(archive_path).ok())
+        .collect();
+    let len = deletion_entries.len();
+    deletion_entries.retain(|deletion_entry| {
+        !fs::canonicalize(&deletion_entry.path).is_ok_and(|path| archives.contains(&path))
+    });
+    len - deletion_entries.len()
+}
+
+/// Sort deletion entries by path.
+fn sort_deletion_entries(deletion_entries: &mut [DeletionEntry]) {
+    deletion_entries.sort_by(|a, b| a.path.cmp(&b.path));
+}
+
+/// Remove deletion entries with the same path. The first one is kept.
+/// Entries are sorted by path if `sort`, otherwise kept in the order of the archive entries.
+fn dedup_deletion_entries(deletion_entries: &mut Vec<DeletionEntry>, sort: bool) {
+    if sort {
+        sort_deletion_entries(deletion_entries);
+    }
+    let mut found_paths = HashSet::new();
+    deletion_entries.retain(|deletion_entry| found_paths.insert(deletion_entry.path.clone()));
+}
+
+/// Sort path by depth, deepest first. Paths of the same depth are sorted by components.
+fn sort_path_by_depth<P: AsRef<Path>>(paths: &mut [P]) {
+    // Count components rather than separators so that a trailing separator
+    // does not make a directory deeper than its contents.
+    paths.sort_by(|a, b| {
+        b.as_ref()
+            .components()
+            .count()
+            .cmp(&a.as_ref().components().count())
+            .then(a.as_ref().cmp(b.as_ref()))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        append_backup_record, atomic_delete, build_deletion_plan, canonicalize_encodings,
+        check_backup_sandbox, check_option_paths_sandbox, check_write_permissions,
+        convert_separator, dedup_deletion_entries, edit_deletion_list, exclude_archives,
+        execute_plan, execute_plan_with_reruns, expand_glob_path, guess_stem_candidates,
+        interpolate_completion_action, interpolate_confirm, is_stdin, list_status,
+        match_content_path, match_strategy, matches, missing_archive_entries, new_remover,
+        normalize_file_name_lossy, normalize_prefix, read_line_with_timeout,
+        remove_empty_directories, remove_file, search_archive, search_content_paths, search_paths,
+        sort_path_by_depth, split_paths, strip_extended_length_prefix, unpack_mode,
+        validate_encodings, Args, BackupRecord, CentralDirectoryFileHeader, DeletionConfig,
+        DeletionEntry, EncodingReport, FileEntry, ListStatus, MatchStrategy, Mode, RemovalStats,
+        SearchOptions, DEFAULT_CONFIRM_MESSAGE,
+    };
+    use clap::Parser;
+    use rmext::remover::RealRemover;
+    use rmext::BACKUP_MANIFEST_FILE_NAME;
+    use std::borrow::Cow;
+    use std::collections::{BTreeMap, HashSet};
+    use std::fs;
+    use std::io::{self, Error, ErrorKind, Read, Result};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    #[test]
+    fn expand_glob_pattern_of_missing_path() {
+        assert_eq!(
+            vec![
+                PathBuf::from("resource/archive.zip"),
+                PathBuf::from("resource/archive2.zip")
+            ],
+            expand_glob_path("resource/archive*.zip", false).unwrap()
+        );
+        assert_eq!(
+            vec![PathBuf::from("resource/archive.zip")],
+            expand_glob_path("resource/archive.zip", true).unwrap()
+        );
+    }
+
+    #[test]
+    fn unpack_all_delete_mode() {
+        let codes = vec![1, 2];
+        assert_eq!(codes, unpack_mode(Mode::Both).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unpack_every_mode() {
+        assert_eq!(vec![1], unpack_mode(Mode::Files).collect::<Vec<_>>());
+        assert_eq!(vec![2], unpack_mode(Mode::Directory).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parse_mode_by_name_or_code() {
+        assert_eq!(Ok(Mode::Both), "both".parse());
+        assert_eq!(Ok(Mode::Both), "3".parse());
+        assert_eq!(Ok(Mode::Directory), "2".parse());
+        assert_eq!(Ok(Mode::Files), "Files".parse());
+        assert!("4".parse::<Mode>().is_err());
+        assert_eq!("directory", Mode::Directory.to_string());
+        let args = Args::parse_from(["rmext", "--mode", "files"]);
+        assert_eq!(Mode::Files, args.mode);
+    }
+
+    #[test]
+    fn trim_dot_slash_in_path() {
+        assert_eq!("A/A/B.txt", normalize_file_name_lossy("../A/../A/./B.txt"));
+        assert!(matches!(
+            normalize_file_name_lossy("A/B.txt"),
+            Cow::Borrowed("A/B.txt")
+        ));
+        assert!(matches!(
+            normalize_file_name_lossy("./A/B.txt"),
+            Cow::Owned(_)
+        ));
+    }
+
+    #[test]
+    fn keep_prefix_inside_search_path() {
+        assert_eq!("extracted", normalize_prefix("/../extracted/"));
+        assert_eq!("", normalize_prefix(".."));
+        assert_eq!("a", normalize_prefix("a/.."));
+        assert_eq!("a", normalize_prefix("a/../.."));
+        assert_eq!("x", normalize_prefix("..\\x"));
+    }
+
+    #[test]
+    fn keep_dot_slash_in_raw_path() {
+        assert_eq!("./.hidden", convert_separator("./.hidden"));
+    }
+
+    #[test]
+    fn detect_readonly_file() {
+        let dir = std::env::temp_dir().join("rmext_detect_readonly_file");
+        fs::create_dir_all(&dir).unwrap();
+        let writable = dir.join("writable.txt");
+        let readonly = dir.join("readonly.txt");
+        fs::write(&writable, "").unwrap();
+        fs::write(&readonly, "").unwrap();
+        let mut permissions = fs::metadata(&readonly).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&readonly, permissions).unwrap();
+
+        let unwritable_paths = check_write_permissions(&[writable, readonly.clone()]);
+
+        let mut permissions = fs::metadata(&readonly).unwrap().permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(&readonly, permissions).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec![readonly], unwritable_paths);
+    }
+
+    #[test]
+    fn count_file_name_encodings() {
+        let header = |file_name: &str, general_purpose_bit_flag, file_name_encoding| {
+            Ok(CentralDirectoryFileHeader {
+                file_name: file_name.to_string(),
+                general_purpose_bit_flag,
+                file_name_encoding,
+                ..Default::default()
+            })
+        };
+        let headers = vec![
+            header("utf8.txt", [0, 0x08], "utf8"),
+            header("cp932.txt", [0, 0], "cp932"),
+            header("\u{FFFD}.txt", [0, 0], "cp932"),
+            Err(Error::new(ErrorKind::InvalidData, "invalid utf-8")),
+        ];
+        assert_eq!(
+            EncodingReport {
+                utf8_flag: 1,
+                fallback: BTreeMap::from([("cp932", 2)]),
+                replacement_chars: 1,
+                errors: 1,
+            },
+            EncodingReport::new(headers.into_iter())
+        );
+    }
+
+    #[test]
+    fn remove_directories_left_by_missing_contents() {
+        let dir = std::env::temp_dir().join("rmext_remove_directories_left_by_missing_contents");
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        fs::create_dir_all(dir.join("c")).unwrap();
+        fs::write(dir.join("c/kept.txt"), "").unwrap();
+        let entry = |file_name: &str| {
+            Ok(FileEntry {
+                file_name: file_name.to_string(),
+                ..Default::default()
+            })
+        };
+        let options = SearchOptions {
+            include_missing: true,
+            ..Default::default()
+        };
+        let entries = vec![entry("a/b/removed.txt"), entry("c/removed.txt")];
+        let deletion_entries = search_content_paths(entries.into_iter(), [&dir], &options)
+            .unwrap()
+            .into_iter()
+            .map(|(path, entry)| DeletionEntry {
+                path,
+                entry,
+                archive_path: dir.join("archive.zip"),
+            })
+            .collect::<Vec<_>>();
+
+        remove_empty_directories(&RealRemover, &deletion_entries, None, true, &mut io::sink())
+            .unwrap();
+
+        let a_exists = dir.join("a").exists();
+        let c_exists = dir.join("c").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(!a_exists);
+        assert!(c_exists);
+    }
+
+    #[test]
+    fn skip_long_path() {
+        let entry = |file_name: &str| {
+            Ok(FileEntry {
+                file_name: file_name.to_string(),
+                ..Default::default()
+            })
+        };
+        let options = SearchOptions {
+            include_missing: true,
+            max_path_length: 12,
+            ..Default::default()
+        };
+        let entries = vec![entry("short.txt"), entry("long_file_name.txt")];
+        let paths = search_content_paths(entries.into_iter(), ["a"], &options)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![PathBuf::from("a").join("short.txt")], paths);
+    }
+
+    #[test]
+    fn ignore_artifact_directories() {
+        let dir = std::env::temp_dir().join("rmext_ignore_artifact_directories");
+        fs::create_dir_all(dir.join("node_modules")).unwrap();
+        fs::write(dir.join("node_modules/package.json"), "").unwrap();
+        fs::write(dir.join("package.json"), "").unwrap();
+        let entries = ["node_modules/package.json", "package.json"].map(|file_name| {
+            Ok(FileEntry {
+                file_name: file_name.to_string(),
+                ..Default::default()
+            })
+        });
+        let options = SearchOptions {
+            ignore_artifact_dirs: true,
+            ..Default::default()
+        };
+        let paths = search_content_paths(entries.into_iter(), [&dir], &options)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec![dir.join("package.json")], paths);
+    }
+
+    #[test]
+    fn prepend_prefix_to_entry_names() {
+        let entry = |file_name: &str| {
+            Ok(FileEntry {
+                file_name: file_name.to_string(),
+                ..Default::default()
+            })
+        };
+        let options = SearchOptions {
+            include_missing: true,
+            prefix: Some(normalize_prefix("/../extracted/")),
+            ..Default::default()
+        };
+        let paths = search_content_paths(vec![entry("src/main.rs")].into_iter(), ["a"], &options)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![PathBuf::from("a").join("extracted/").join("src/main.rs")],
+            paths
+        );
+    }
+
+    #[test]
+    fn find_archive_entries_missing_on_disk() {
+        let dir = std::env::temp_dir().join("rmext_find_archive_entries_missing_on_disk");
+        fs::create_dir_all(&dir).unwrap();
+        let archive_path = dir.join("archive2.zip");
+        fs::copy("resource/archive2.zip", &archive_path).unwrap();
+        let args = Args::parse_from(["rmext", "--mode", "1"]);
+        let options = SearchOptions {
+            ..Default::default()
+        };
+
+        let missing_before = missing_archive_entries(&archive_path, &args, &options).unwrap();
+        // Any size counts as extracted.
+        fs::write(dir.join("file.txt"), "modified").unwrap();
+        let missing_after = missing_archive_entries(&archive_path, &args, &options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec!["file.txt".to_string()], missing_before);
+        assert!(missing_after.is_empty());
+    }
+
+    #[test]
+    fn match_by_crc_regardless_of_size() {
+        let dir = std::env::temp_dir().join("rmext_match_by_crc_regardless_of_size");
+        fs::create_dir_all(&dir).unwrap();
+        // Padded with spaces after extraction.
+        fs::write(dir.join("a.txt"), "abc   ").unwrap();
+        let entry = || {
+            Ok(FileEntry {
+                file_name: "a.txt".to_string(),
+                uncompressed_size: 3,
+                crc32: Some(crc32fast::hash(b"abc   ")),
+                ..Default::default()
+            })
+        };
+        let search = |match_strategy| {
+            let options = SearchOptions {
+                match_strategy,
+                ..Default::default()
+            };
+            search_content_paths([entry()].into_iter(), [&dir], &options)
+                .unwrap()
+                .len()
+        };
+
+        let matched_by_crc = search(MatchStrategy::NameAndSizeOrCrc);
+        let matched_by_size = search(MatchStrategy::NameAndSize);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(1, matched_by_crc);
+        assert_eq!(0, matched_by_size);
+    }
+
+    #[test]
+    fn match_files_by_strategy() {
+        let dir = std::env::temp_dir().join("rmext_match_files_by_strategy");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("same.txt"), "abc").unwrap();
+        fs::write(dir.join("edited.txt"), "xyz").unwrap();
+        fs::write(dir.join("resized.txt"), "abc   ").unwrap();
+        let entry = |data: &[u8]| FileEntry {
+            uncompressed_size: 3,
+            crc32: Some(crc32fast::hash(data)),
+            ..Default::default()
+        };
+        let matched = |strategy| {
+            [
+                ("same.txt", entry(b"abc")),
+                ("edited.txt", entry(b"abc")),
+                ("resized.txt", entry(b"abc   ")),
+                ("missing.txt", entry(b"abc")),
+            ]
+            .iter()
+            .filter(|(name, entry)| matches(entry, &dir.join(name), strategy))
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+        };
+
+        let name_only = matched(MatchStrategy::NameOnly);
+        let name_and_size = matched(MatchStrategy::NameAndSize);
+        let name_and_crc = matched(MatchStrategy::NameAndCrc);
+        let name_size_and_crc = matched(MatchStrategy::NameSizeAndCrc);
+        let name_and_size_or_crc = matched(MatchStrategy::NameAndSizeOrCrc);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec!["same.txt", "edited.txt", "resized.txt"], name_only);
+        assert_eq!(vec!["same.txt", "edited.txt"], name_and_size);
+        assert_eq!(vec!["same.txt", "resized.txt"], name_and_crc);
+        assert_eq!(vec!["same.txt"], name_size_and_crc);
+        assert_eq!(
+            vec!["same.txt", "edited.txt", "resized.txt"],
+            name_and_size_or_crc
+        );
+    }
+
+    #[test]
+    fn match_renamed_files_only_if_enabled() {
+        let dir = std::env::temp_dir().join("rmext_match_renamed_files_only_if_enabled");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "abd").unwrap();
+        fs::write(dir.join("a.bak"), "abc").unwrap();
+        let search = |match_renamed| {
+            let entry = FileEntry {
+                file_name: "a.txt".to_string(),
+                uncompressed_size: 3,
+                crc32: Some(crc32fast::hash(b"abc")),
+                ..Default::default()
+            };
+            let options = SearchOptions {
+                match_strategy: MatchStrategy::CrcOnly,
+                match_renamed,
+                ..Default::default()
+            };
+            search_content_paths([Ok(entry)].into_iter(), [&dir], &options)
+                .unwrap()
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>()
+        };
+
+        let renamed = search(true);
+        let named = search(false);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec![dir.join("a.bak")], renamed);
+        assert!(named.is_empty());
+    }
+
+    #[test]
+    fn match_renamed_bare_names_by_crc() {
+        // Tests run in the package root.
+        let entry = FileEntry {
+            file_name: "Cargo.txt".to_string(),
+            crc32: Some(crc32fast::hash(&fs::read("Cargo.toml").unwrap())),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            match_strategy: MatchStrategy::CrcOnly,
+            match_renamed: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Some(PathBuf::from("Cargo.toml")),
+            match_content_path(&entry, PathBuf::from("Cargo.txt"), &options)
+        );
+        assert_eq!(
+            None,
+            match_content_path(&entry, PathBuf::from("/"), &options)
+        );
+    }
+
+    #[test]
+    fn map_alias_flags_to_match_strategies() {
+        let strategy = |args: &[&str]| match_strategy(&Args::parse_from(args));
+        assert_eq!(MatchStrategy::NameAndSize, strategy(&["rmext"]));
+        assert_eq!(MatchStrategy::CrcOnly, strategy(&["rmext", "--trust-crc"]));
+        assert_eq!(
+            MatchStrategy::NameAndSizeOrCrc,
+            strategy(&["rmext", "--match-by-crc-first"])
+        );
+        assert_eq!(
+            MatchStrategy::NameSizeAndCrc,
+            strategy(&["rmext", "--match-strategy", "name-size-and-crc"])
+        );
+        for alias in ["--trust-crc", "--match-by-crc-first"] {
+            assert!(
+                Args::try_parse_from(["rmext", alias, "--match-strategy", "name-only"]).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn interpolate_stats_in_completion_action() {
+        let stats = RemovalStats {
+            files: 3,
+            bytes: 1024,
+            ..Default::default()
+        };
+        let expected = if cfg!(windows) {
+            r#"notify 3 files 1024 bytes "a.zip" "b.zip""#
+        } else {
+            "notify 3 files 1024 bytes 'a.zip' 'b.zip'"
+        };
+        assert_eq!(
+            expected,
+            interpolate_completion_action(
+                "notify %n files %s bytes %a",
+                &stats,
+                &[PathBuf::from("a.zip"), PathBuf::from("b.zip")]
+            )
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn quote_archive_paths_in_completion_action() {
+        let archive_paths = [
+            PathBuf::from("x$(rm -rf ~).zip"),
+            PathBuf::from("a b.zip"),
+            PathBuf::from("it's.zip"),
+        ];
+        assert_eq!(
+            r"echo 'x$(rm -rf ~).zip' 'a b.zip' 'it'\''s.zip'",
+            interpolate_completion_action("echo %a", &RemovalStats::default(), &archive_paths)
+        );
+    }
+
+    #[test]
+    fn exclude_archive_itself_from_entries() {
+        let dir = std::env::temp_dir().join("rmext_exclude_archive_itself_from_entries");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("archive.zip"), "").unwrap();
+        fs::write(dir.join("a.txt"), "").unwrap();
+        let mut deletion_entries = ["archive.zip", "./a.txt", "./archive.zip"]
+            .iter()
+            .map(|file_name| DeletionEntry {
+                path: dir.join(file_name),
+                entry: FileEntry::default(),
+                archive_path: dir.join("archive.zip"),
+            })
+            .collect::<Vec<_>>();
+
+        let excluded = exclude_archives(&mut deletion_entries, &[dir.join("archive.zip")]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(2, excluded);
+        assert_eq!(
+            vec![dir.join("./a.txt")],
+            deletion_entries
+                .into_iter()
+                .map(|deletion_entry| deletion_entry.path)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn interpolate_count_and_size_in_confirm_message() {
+        assert_eq!(
+            "Delete 3 files (1.2 MiB)? [Y/n] ",
+            interpolate_confirm("Delete %n files (%s)? [Y/n] ", 3, 1258291)
+        );
+        assert_eq!(
+            DEFAULT_CONFIRM_MESSAGE,
+            interpolate_confirm(DEFAULT_CONFIRM_MESSAGE, 3, 5)
+        );
+    }
+
+    #[test]
+    fn time_out_reading_line() {
+        struct SlowReader;
+        impl Read for SlowReader {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+                std::thread::sleep(Duration::from_secs(5));
+                buf[0] = b'y';
+                Ok(1)
+            }
+        }
+
+        assert_eq!(
+            Some("y\n".to_string()),
+            read_line_with_timeout(io::Cursor::new(b"y\n".to_vec()), Duration::from_secs(5))
+        );
+        assert_eq!(
+            None,
+            read_line_with_timeout(io::BufReader::new(SlowReader), Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn skip_hidden_entries() {
+        let entry = |file_name: &str| {
+            Ok(FileEntry {
+                file_name: file_name.to_string(),
+                ..Default::default()
+            })
+        };
+        let options = SearchOptions {
+            include_missing: true,
+            skip_hidden: true,
+            ..Default::default()
+        };
+        let entries = vec![
+            entry("a.txt"),
+            entry(".env"),
+            entry("sub/.git/config"),
+            entry("./sub/b.txt"),
+        ];
+        let paths = search_content_paths(entries.into_iter(), ["a"], &options)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                PathBuf::from("a").join("a.txt"),
+                PathBuf::from("a").join("sub/b.txt")
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn limit_depth_of_removing_directories() {
+        let dir = std::env::temp_dir().join("rmext_limit_depth_of_removing_directories");
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        let deletion_entries = vec![DeletionEntry {
+            path: dir.join("a/b/removed.txt"),
+            entry: FileEntry {
+                file_name: "a/b/removed.txt".to_string(),
+                ..Default::default()
+            },
+            archive_path: dir.join("archive.zip"),
+        }];
+
+        remove_empty_directories(
+            &RealRemover,
+            &deletion_entries,
+            Some(1),
+            true,
+            &mut io::sink(),
+        )
+        .unwrap();
+
+        let a_exists = dir.join("a").exists();
+        let b_exists = dir.join("a/b").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(a_exists);
+        assert!(!b_exists);
+    }
+
+    #[test]
+    fn move_back_files_when_atomic_delete_fails() {
+        let dir = std::env::temp_dir().join("rmext_move_back_files_when_atomic_delete_fails");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/kept.txt"), "").unwrap();
+        let deletion_entry = |file_name: &str| DeletionEntry {
+            path: dir.join(file_name),
+            entry: FileEntry::default(),
+            archive_path: dir.join("archive.zip"),
+        };
+        let kept = deletion_entry("sub/kept.txt");
+        let missing = deletion_entry("missing.txt");
+
+        let result = atomic_delete(&[&kept, &missing]);
+        let kept_exists = kept.path.exists();
+        let remaining = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+        assert!(kept_exists);
+        assert_eq!(1, remaining);
+    }
+
+    #[test]
+    fn remove_empty_archive_stem_directory() {
+        let dir = std::env::temp_dir().join("rmext_remove_empty_archive_stem_directory");
+        fs::create_dir_all(dir.join("archive/sub")).unwrap();
+        fs::write(dir.join("archive/a.txt"), "").unwrap();
+        fs::write(dir.join("archive/sub/b.txt"), "").unwrap();
+        let archive_path = dir.join("archive.zip");
+        let search_path = search_paths(&archive_path, Mode::Directory).remove(0);
+        let deletion_entries = ["a.txt", "sub/b.txt"]
+            .iter()
+            .map(|file_name| DeletionEntry {
+                path: search_path.join(file_name),
+                entry: FileEntry::default(),
+                archive_path: archive_path.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        for deletion_entry in &deletion_entries {
+            remove_file(&RealRemover, &deletion_entry.path).unwrap();
+        }
+        let mut out = Vec::new();
+        let removed =
+            remove_empty_directories(&RealRemover, &deletion_entries, None, true, &mut out)
+                .unwrap();
+
+        let stem_exists = dir.join("archive").exists();
+        let dir_exists = dir.exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(2, removed);
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            1,
+            out.matches("Remove empty directory recursively.").count()
+        );
+        assert!(out.starts_with("Remove empty directory recursively.\n\tRemoved: "));
+        assert!(!stem_exists);
+        assert!(dir_exists);
+    }
+
+    #[test]
+    fn remove_no_directories_when_not_empty() {
+        let dir = std::env::temp_dir().join("rmext_remove_no_directories_when_not_empty");
+        fs::create_dir_all(dir.join("archive/sub")).unwrap();
+        fs::write(dir.join("archive/sub/kept.txt"), "").unwrap();
+        let archive_path = dir.join("archive.zip");
+        let search_path = search_paths(&archive_path, Mode::Directory).remove(0);
+        let deletion_entries = vec![DeletionEntry {
+            path: search_path.join("sub/removed.txt"),
+            entry: FileEntry::default(),
+            archive_path: archive_path.clone(),
+        }];
+
+        let mut out = Vec::new();
+        let removed =
+            remove_empty_directories(&RealRemover, &deletion_entries, None, true, &mut out)
+                .unwrap();
+
+        let sub_exists = dir.join("archive/sub").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(0, removed);
+        assert!(!String::from_utf8(out)
+            .unwrap()
+            .contains("Remove empty directory recursively."));
+        assert!(sub_exists);
+    }
+
+    #[test]
+    fn keep_archive_order_without_sort() {
+        let deletion_entries = || {
+            ["b.txt", "a.txt", "c/d.txt", "a.txt"]
+                .iter()
+                .map(|path| DeletionEntry {
+                    path: PathBuf::from(path),
+                    entry: FileEntry::default(),
+                    archive_path: PathBuf::from("archive.zip"),
+                })
+                .collect::<Vec<_>>()
+        };
+        let paths = |deletion_entries: &[DeletionEntry]| {
+            deletion_entries
+                .iter()
+                .map(|deletion_entry| deletion_entry.path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let mut unsorted = deletion_entries();
+        dedup_deletion_entries(&mut unsorted, false);
+        let mut sorted = deletion_entries();
+        dedup_deletion_entries(&mut sorted, true);
+        assert_eq!(vec!["b.txt", "a.txt", "c/d.txt"], paths(&unsorted));
+        assert_eq!(vec!["a.txt", "b.txt", "c/d.txt"], paths(&sorted));
+    }
+
+    #[test]
+    fn split_paths_by_delimiter() {
+        assert_eq!(
+            vec!["a.zip", "b.zip"],
+            split_paths("a.zip\r\n\nb.zip\n", '\n')
+        );
+        assert_eq!(
+            vec!["/tmp/a.zip", "b.zip", "c d.zip"],
+            split_paths("/tmp/a.zip:b.zip::c d.zip\n", ':')
+        );
+    }
+
+    #[test]
+    fn ignore_stdin_with_no_stdin_flag() {
+        let request = "-".to_string();
+        assert!(is_stdin(Some(&request), false));
+        assert!(!is_stdin(Some(&request), true));
+        assert!(!is_stdin(None, true));
+    }
+
+    #[test]
+    fn strip_windows_extended_length_prefix() {
+        assert_eq!(
+            r"Users\Alice\file.txt",
+            strip_extended_length_prefix(r"\\?\C:\Users\Alice\file.txt")
+        );
+        assert_eq!(
+            r"dir\file.txt",
+            strip_extended_length_prefix(r"\\?\UNC\server\share\dir\file.txt")
+        );
+        assert_eq!("a/b.txt", strip_extended_length_prefix("a/b.txt"));
+        let expected = if cfg!(windows) {
+            r"Users\Alice\file.txt"
+        } else {
+            "Users/Alice/file.txt"
+        };
+        assert_eq!(
+            expected,
+            normalize_file_name_lossy(r"\\?\C:\Users\Alice\file.txt")
+        );
+    }
+
+    #[test]
+    fn list_status_of_archive_contents() {
+        let dir = std::env::temp_dir().join("rmext_list_status_of_archive_contents");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("found.txt"), "abc").unwrap();
+        fs::write(dir.join("size.txt"), "abcd").unwrap();
+        let deletion_entry = |file_name: &str, is_directory: bool| DeletionEntry {
+            path: dir.join(file_name),
+            entry: FileEntry {
+                file_name: file_name.to_string(),
+                uncompressed_size: if is_directory { 0 } else { 3 },
+                is_directory,
+                ..Default::default()
+            },
+            archive_path: dir.join("archive.zip"),
+        };
+
+        let mut statuses = vec![
+            list_status(&deletion_entry("size.txt", false)),
+            list_status(&deletion_entry("missing.txt", false)),
+            list_status(&deletion_entry("found.txt", false)),
+            list_status(&deletion_entry("sub", true)),
+        ];
+        fs::remove_dir_all(&dir).unwrap();
+        statuses.sort();
+        assert_eq!(
+            vec![
+                ListStatus::Found,
+                ListStatus::Found,
+                ListStatus::NotFound,
+                ListStatus::SizeMismatch(4)
+            ],
+            statuses
+        );
+    }
+
+    #[test]
+    fn preserve_directories_matching_archive_entries() {
+        let dir = std::env::temp_dir().join("rmext_preserve_directories_matching_archive_entries");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::create_dir_all(dir.join("name")).unwrap();
+        let entry = |file_name: &str, is_directory: bool| {
+            Ok(FileEntry {
+                file_name: file_name.to_string(),
+                is_directory,
+                ..Default::default()
+            })
+        };
+        let options = SearchOptions {
+            include_missing: true,
+            preserve_dirs: true,
+            ..Default::default()
+        };
+        let entries = vec![
+            entry("sub/", true),
+            entry("name", false),
+            entry("sub/a.txt", false),
+        ];
+
+        let paths = search_content_paths(entries.into_iter(), [&dir], &options)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(vec![dir.join("sub/a.txt")], paths);
+    }
+
+    #[test]
+    fn build_plan_without_removing() {
+        let dir = std::env::temp_dir().join("rmext_build_plan_without_removing");
+        fs::create_dir_all(dir.join("archive/sub")).unwrap();
+        fs::write(dir.join("archive/a.txt"), "abc").unwrap();
+        fs::write(dir.join("archive/sub/b.txt"), "de").unwrap();
+        let deletion_entries = ["a.txt", "sub/b.txt"]
+            .iter()
+            .map(|file_name| DeletionEntry {
+                path: dir.join("archive").join(file_name),
+                entry: FileEntry::default(),
+                archive_path: dir.join("archive.zip"),
+            })
+            .collect::<Vec<_>>();
+
+        let plan = build_deletion_plan(
+            deletion_entries,
+            DeletionConfig {
+                recursive: true,
+                ..Default::default()
+            },
+        );
+        let planned_files_exist = plan.entries.iter().all(|entry| entry.path.exists());
+        let stats = execute_plan(&plan).unwrap();
+        let archive_dir_exists = dir.join("archive").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            RemovalStats {
+                files: 2,
+                bytes: 5,
+                ..Default::default()
+            },
+            plan.stats
+        );
+        assert!(planned_files_exist);
+        assert_eq!(
+            RemovalStats {
+                files: 2,
+                bytes: 5,
+                removed: vec![
+                    (dir.join("archive/a.txt"), 3),
+                    (dir.join("archive/sub/b.txt"), 2)
+                ],
+                ..Default::default()
+            },
+            stats
+        );
+        assert!(!archive_dir_exists);
+    }
+
+    #[test]
+    fn append_only_removed_paths_to_log() {
+        let dir = std::env::temp_dir().join("rmext_append_only_removed_paths_to_log");
+        fs::create_dir_all(dir.join("archive")).unwrap();
+        let log = dir.join("deleted.txt");
+        let plan = |file_names: &[&str]| {
+            let deletion_entries = file_names
+                .iter()
+                .map(|file_name| DeletionEntry {
+                    path: dir.join("archive").join(file_name),
+                    entry: FileEntry::default(),
+                    archive_path: dir.join("archive.zip"),
+                })
+                .collect();
+            build_deletion_plan(
+                deletion_entries,
+                DeletionConfig {
+                    deleted_paths_log: Some(log.clone()),
+                    ..Default::default()
+                },
+            )
+        };
+        fs::write(dir.join("archive/a.txt"), "").unwrap();
+        fs::write(dir.join("archive/c.txt"), "").unwrap();
+
+        execute_plan(&plan(&["a.txt", "missing.txt"])).unwrap();
+        execute_plan(&plan(&["c.txt"])).unwrap();
+        let content = fs::read_to_string(&log).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            format!(
+                "{}\n{}\n",
+                dir.join("archive/a.txt").to_string_lossy(),
+                dir.join("archive/c.txt").to_string_lossy()
+            ),
+            content
+        );
+    }
+
+    #[test]
+    fn terminate_logged_paths_by_nul_with_print0() {
+        let dir = std::env::temp_dir().join("rmext_terminate_logged_paths_by_nul_with_print0");
+        fs::create_dir_all(dir.join("archive")).unwrap();
+        let log = dir.join("deleted.txt");
+        let deletion_entries = ["a.txt", "b.txt"]
+            .iter()
+            .map(|file_name| DeletionEntry {
+                path: dir.join("archive").join(file_name),
+                entry: FileEntry::default(),
+                archive_path: dir.join("archive.zip"),
+            })
+            .collect();
+        fs::write(dir.join("archive/a.txt"), "").unwrap();
+        fs::write(dir.join("archive/b.txt"), "").unwrap();
+        let plan = build_deletion_plan(
+            deletion_entries,
+            DeletionConfig {
+                deleted_paths_log: Some(log.clone()),
+                print0: true,
+                ..Default::default()
+            },
+        );
+
+        execute_plan(&plan).unwrap();
+        let content = fs::read(&log).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let mut expected = Vec::new();
+        for file_name in ["a.txt", "b.txt"] {
+            expected.extend_from_slice(
+                dir.join("archive")
+                    .join(file_name)
+                    .as_os_str()
+                    .as_encoded_bytes(),
+            );
+            expected.push(b'\0');
+        }
+        assert_eq!(expected, content);
+    }
+
+    #[test]
+    fn stop_at_first_failure_with_fail_fast() {
+        let dir = std::env::temp_dir().join("rmext_stop_at_first_failure_with_fail_fast");
+        // A directory which is not empty can not be removed.
+        fs::create_dir_all(dir.join("archive/b.txt/nested")).unwrap();
+        fs::write(dir.join("archive/a.txt"), "").unwrap();
+        fs::write(dir.join("archive/c.txt"), "").unwrap();
+        let plan = |fail_fast| {
+            let deletion_entries = ["a.txt", "b.txt", "c.txt"]
+                .iter()
+                .map(|file_name| DeletionEntry {
+                    path: dir.join("archive").join(file_name),
+                    entry: FileEntry::default(),
+                    archive_path: dir.join("archive.zip"),
+                })
+                .collect();
+            build_deletion_plan(
+                deletion_entries,
+                DeletionConfig {
+                    recursive: true,
+                    fail_fast,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let stats = execute_plan(&plan(true)).unwrap();
+        let c_exists = dir.join("archive/c.txt").exists();
+        let continued_stats = execute_plan(&plan(false)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(1, stats.files);
+        assert_eq!(1, stats.failed);
+        assert_eq!(Some(1), stats.errors_before_abort);
+        assert!(c_exists);
+        assert_eq!(1, continued_stats.files);
+        assert_eq!(None, continued_stats.errors_before_abort);
+    }
+
+    #[test]
+    fn rerun_only_failed_files() {
+        let dir = std::env::temp_dir().join("rmext_rerun_only_failed_files");
+        fs::create_dir_all(dir.join("archive/b.txt/nested")).unwrap();
+        fs::write(dir.join("archive/a.txt"), "abc").unwrap();
+        let deletion_entries = ["a.txt", "b.txt"]
+            .iter()
+            .map(|file_name| DeletionEntry {
+                path: dir.join("archive").join(file_name),
+                entry: FileEntry::default(),
+                archive_path: dir.join("archive.zip"),
+            })
+            .collect();
+        let log = dir.join("deleted.txt");
+        let plan = build_deletion_plan(
+            deletion_entries,
+            DeletionConfig {
+                deleted_paths_log: Some(log.clone()),
+                ..Default::default()
+            },
+        );
+
+        let stats = execute_plan_with_reruns(&plan, 3).unwrap();
+        let logged_lines = fs::read_to_string(&log).unwrap().lines().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            RemovalStats {
+                files: 1,
+                bytes: 3,
+                failed: 1,
+                errors_before_abort: None,
+                removed: vec![(dir.join("archive/a.txt"), 3)],
+            },
+            stats
+        );
+        assert_eq!(1, logged_lines);
+    }
+
+    #[test]
+    fn stop_after_max_errors() {
+        let dir = std::env::temp_dir().join("rmext_stop_after_max_errors");
+        for file_name in ["b.txt", "d.txt", "f.txt"] {
+            fs::create_dir_all(dir.join("archive").join(file_name).join("nested")).unwrap();
+        }
+        for file_name in ["a.txt", "c.txt", "e.txt", "g.txt"] {
+            fs::write(dir.join("archive").join(file_name), "").unwrap();
+        }
+        let deletion_entries = [
+            "a.txt", "b.txt", "c.txt", "d.txt", "e.txt", "f.txt", "g.txt",
+        ]
+        .iter()
+        .map(|file_name| DeletionEntry {
+            path: dir.join("archive").join(file_name),
+            entry: FileEntry::default(),
+            archive_path: dir.join("archive.zip"),
+        })
+        .collect();
+        let plan = build_deletion_plan(
+            deletion_entries,
+            DeletionConfig {
+                max_errors: Some(2),
+                verbose: true,
+                ..Default::default()
+            },
+        );
+
+        let stats = execute_plan(&plan).unwrap();
+        let e_exists = dir.join("archive/e.txt").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(2, stats.files);
+        assert_eq!(2, stats.failed);
+        assert_eq!(Some(2), stats.errors_before_abort);
+        assert!(e_exists);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn curate_list_with_editor() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join("rmext_curate_list_with_editor");
+        fs::create_dir_all(&dir).unwrap();
+        let editor = dir.join("editor.sh");
+        // Strip the third line, the second path after the comment line.
+        fs::write(&editor, "#!/bin/sh\nsed -i 3d \"$1\"\n").unwrap();
+        fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+        let paths = vec![
+            PathBuf::from("a/1.txt"),
+            PathBuf::from("a/2.txt"),
+            PathBuf::from("a/3.txt"),
+        ];
+
+        let curated = edit_deletion_list(&paths, &editor.to_string_lossy()).unwrap();
+        let unchanged = edit_deletion_list(&paths, "true").unwrap();
+        let failed = edit_deletion_list(&paths, "false").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            Some(HashSet::from([
+                PathBuf::from("a/1.txt"),
+                PathBuf::from("a/3.txt")
+            ])),
+            curated
+        );
+        assert_eq!(None, unchanged);
+        assert_eq!(None, failed);
+    }
+
+    #[test]
+    fn sort_path_by_depth_deepest_first() {
+        let mut paths = vec![
+            PathBuf::from("a"),
+            PathBuf::from("a/b/c"),
+            PathBuf::from("a/b"),
+        ];
+        sort_path_by_depth(&mut paths);
+        assert_eq!(
+            vec![
+                PathBuf::from("a/b/c"),
+                PathBuf::from("a/b"),
+                PathBuf::from("a")
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn sort_path_by_depth_equal_depth_by_path() {
+        let mut paths = vec![
+            PathBuf::from("b/x"),
+            PathBuf::from("a b/y"),
+            PathBuf::from("a/z"),
+            PathBuf::from("a-b/w"),
+        ];
+        sort_path_by_depth(&mut paths);
+        // Compared by components, so `a/z` comes before `a b/y` though ' ' < '/'.
+        assert_eq!(
+            vec![
+                PathBuf::from("a/z"),
+                PathBuf::from("a b/y"),
+                PathBuf::from("a-b/w"),
+                PathBuf::from("b/x")
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn sort_path_by_depth_single_path() {
+        let mut paths = vec![PathBuf::from("a")];
+        sort_path_by_depth(&mut paths);
+        assert_eq!(vec![PathBuf::from("a")], paths);
+        let mut paths: Vec<PathBuf> = Vec::new();
+        sort_path_by_depth(&mut paths);
+        assert!(paths.is_empty());
+    }
+
+    #[test]
+    fn sort_path_by_depth_unicode() {
+        let mut paths = vec![
+            PathBuf::from("テスト"),
+            PathBuf::from("テスト/サブ"),
+            PathBuf::from("テスト/あ"),
+        ];
+        sort_path_by_depth(&mut paths);
+        assert_eq!(
+            vec![
+                PathBuf::from("テスト/あ"),
+                PathBuf::from("テスト/サブ"),
+                PathBuf::from("テスト")
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn sort_path_by_depth_ignores_trailing_separator() {
+        let mut paths = vec![PathBuf::from("a/"), PathBuf::from("a/b")];
+        sort_path_by_depth(&mut paths);
+        assert_eq!(vec![PathBuf::from("a/b"), PathBuf::from("a/")], paths);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn sort_path_by_depth_with_drive_letter() {
+        let mut paths = vec![
+            PathBuf::from(r"C:\a"),
+            PathBuf::from(r"C:\a\b"),
+            PathBuf::from(r"D:\a\b"),
+        ];
+        sort_path_by_depth(&mut paths);
+        assert_eq!(
+            vec![
+                PathBuf::from(r"C:\a\b"),
+                PathBuf::from(r"D:\a\b"),
+                PathBuf::from(r"C:\a")
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn guess_stem_candidates_from_archive_name() {
+        assert_eq!(
+            vec!["Archive_1.0.2", "archive_1.0.2", "Archive", "Archive_1"],
+            guess_stem_candidates("Archive_1.0.2")
+        );
+        assert_eq!(vec!["archive-v1"], guess_stem_candidates("archive-v1"));
+        assert_eq!(vec!["data.tar", "data"], guess_stem_candidates("data.tar"));
+        assert_eq!(vec!["-1"], guess_stem_candidates("-1"));
+        assert_eq!(vec!["pkg-2..1", "pkg-2"], guess_stem_candidates("pkg-2..1"));
+    }
+
+    #[test]
+    fn guess_extraction_dir_with_most_matches() {
+        let dir = std::env::temp_dir().join("rmext_guess_extraction_dir_with_most_matches");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("Archive2-1.0")).unwrap();
+        fs::create_dir_all(dir.join("Archive2")).unwrap();
+        fs::write(dir.join("Archive2/file.txt"), "").unwrap();
+        let archive_path = dir.join("Archive2-1.0.zip");
+        fs::copy("resource/archive2.zip", &archive_path).unwrap();
+        let args = Args::parse_from(["rmext", "--mode", "2", "--guess-stem"]);
+        let options = SearchOptions {
+            ..Default::default()
+        };
+        let guessed = search_archive(&archive_path, &args, &options).unwrap();
+        let args = Args::parse_from(["rmext", "--mode", "2"]);
+        let not_guessed = search_archive(&archive_path, &args, &options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let paths: Vec<PathBuf> = guessed
+            .deletion_entries
+            .into_iter()
+            .map(|deletion_entry| deletion_entry.path)
+            .collect();
+        assert_eq!(vec![dir.join("Archive2").join("file.txt")], paths);
+        assert!(not_guessed.deletion_entries.is_empty());
+    }
+
+    #[test]
+    fn count_only_conflicts_with_total_size_only() {
+        assert!(Args::try_parse_from(["rmext", "--count-only", "--mode", "1"]).is_ok());
+        assert!(Args::try_parse_from(["rmext", "--count-only", "--total-size-only"]).is_err());
+    }
+
+    #[test]
+    fn canonicalize_encodings_of_subcommands() {
+        let mut args = Args::parse_from(["rmext", "check", "--encoding", "sjis", "a.zip"]);
+        canonicalize_encodings(&mut args);
+        assert_eq!("cp932", args.encoding);
+    }
+
+    #[test]
+    fn reject_unsupported_encodings() {
+        let mut args = Args::parse_from(["rmext", "--encoding", "sjis"]);
+        canonicalize_encodings(&mut args);
+        assert!(validate_encodings(&args).is_ok());
+        let args = Args::parse_from(["rmext", "--output-encoding", "latin1"]);
+        assert!(validate_encodings(&args).is_err());
+    }
+
+    #[test]
+    fn allowed_encodings_are_listed() {
+        for encoding in crate::ALLOWED_ENCODINGS {
+            assert_eq!(
+                Some(*encoding),
+                rmext::config::encodings::canonical_encoding(encoding)
+            );
+        }
+    }
+
+    #[test]
+    fn search_content_paths_in_multiple_directories() {
+        let dir = std::env::temp_dir().join("rmext_search_content_paths_in_multiple_directories");
+        fs::create_dir_all(dir.join("archive")).unwrap();
+        for path in ["a.txt", "b.txt", "archive/a.txt", "archive/b.txt"] {
+            fs::write(dir.join(path), "").unwrap();
+        }
+        let entries = ["a.txt", "b.txt"].map(|file_name| {
+            Ok(FileEntry {
+                file_name: file_name.to_string(),
+                ..Default::default()
+            })
+        });
+        let options = SearchOptions {
+            ..Default::default()
+        };
+        let paths = search_content_paths(
+            entries.into_iter(),
+            [dir.clone(), dir.join("archive")],
+            &options,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        // Grouped by search path rather than by entry.
+        assert_eq!(
+            vec![
+                dir.join("a.txt"),
+                dir.join("b.txt"),
+                dir.join("archive/a.txt"),
+                dir.join("archive/b.txt")
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn simulate_removal_without_touching_files() {
+        let dir = std::env::temp_dir().join("rmext_simulate_removal_without_touching_files");
+        fs::create_dir_all(dir.join("archive/sub")).unwrap();
+        fs::write(dir.join("archive/a.txt"), "abc").unwrap();
+        fs::write(dir.join("archive/sub/b.txt"), "de").unwrap();
+        let deletion_entries = ["a.txt", "sub/b.txt"]
+            .iter()
+            .map(|file_name| DeletionEntry {
+                path: dir.join("archive").join(file_name),
+                entry: FileEntry::default(),
+                archive_path: dir.join("archive.zip"),
+            })
+            .collect::<Vec<_>>();
+
+        let plan = build_deletion_plan(
+            deletion_entries,
+            DeletionConfig {
+                recursive: true,
+                simulate_readonly: true,
+                ..Default::default()
+            },
+        );
+        let stats = execute_plan(&plan).unwrap();
+        let files_exist = plan.entries.iter().all(|entry| entry.path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(2, stats.files);
+        assert_eq!(5, stats.bytes);
+        assert!(files_exist);
+    }
+
+    #[test]
+    fn refuse_option_paths_outside_sandbox() {
+        let dir = std::env::temp_dir().join("rmext_refuse_option_paths_outside_sandbox");
+        let _ = fs::remove_dir_all(&dir);
+        let sandbox = dir.join("sandbox");
+        fs::create_dir_all(&sandbox).unwrap();
+        let check = |option: &str, path: PathBuf| {
+            let args = Args::parse_from(["rmext", option, path.to_str().unwrap()]);
+            check_option_paths_sandbox(&args, &sandbox)
+        };
+        let inside = check("--log-deleted-paths-to", sandbox.join("deleted.log"));
+        let log_outside = check("--log-deleted-paths-to", dir.join("deleted.log"));
+        let path_file_outside = check("--path-file", dir.join("paths.txt"));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(inside.is_ok());
+        assert!(log_outside.is_err());
+        assert!(path_file_outside.is_err());
+    }
+
+    #[test]
+    fn refuse_to_restore_outside_sandbox() {
+        let dir = std::env::temp_dir().join("rmext_refuse_to_restore_outside_sandbox");
+        let _ = fs::remove_dir_all(&dir);
+        let sandbox = dir.join("sandbox");
+        let backup_dir = sandbox.join("backup");
+        fs::create_dir_all(&backup_dir).unwrap();
+        let manifest = backup_dir.join(BACKUP_MANIFEST_FILE_NAME);
+        let record = |original: PathBuf| BackupRecord {
+            original,
+            backup: PathBuf::from("a.txt"),
+        };
+        append_backup_record(&manifest, &record(sandbox.join("a.txt"))).unwrap();
+        let inside = check_backup_sandbox(&manifest, &backup_dir, &sandbox);
+        append_backup_record(&manifest, &record(dir.join("a.txt"))).unwrap();
+        let outside = check_backup_sandbox(&manifest, &backup_dir, &sandbox);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(inside.is_ok());
+        assert!(outside.is_err());
+    }
+
+    #[test]
+    fn record_backups_when_atomic_delete_fails() {
+        let dir = std::env::temp_dir().join("rmext_record_backups_when_atomic_delete_fails");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "abc").unwrap();
+        let backup_dir = dir.join("backup");
+        // The second rename fails since the file is already moved.
+        let deletion_entries = ["a.txt", "a.txt"]
+            .iter()
+            .map(|file_name| DeletionEntry {
+                path: dir.join(file_name),
+                entry: FileEntry::default(),
+                archive_path: dir.join("archive.zip"),
+            })
+            .collect::<Vec<_>>();
+
+        let plan = build_deletion_plan(
+            deletion_entries,
+            DeletionConfig {
+                backup_dir: Some(backup_dir.clone()),
+                atomic_delete: true,
+                ..Default::default()
+            },
+        );
+        let result = execute_plan(&plan);
+        let file_exists = dir.join("a.txt").exists();
+        let manifest = fs::read_to_string(backup_dir.join(BACKUP_MANIFEST_FILE_NAME));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+        assert!(file_exists);
+        assert!(manifest.unwrap().contains("a.txt"));
+    }
+
+    #[test]
+    fn keep_archive_with_contents_failed_to_remove() {
+        let dir = std::env::temp_dir().join("rmext_keep_archive_with_contents_failed_to_remove");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/kept.txt"), "").unwrap();
+        fs::write(dir.join("a.txt"), "").unwrap();
+        fs::write(dir.join("archive.zip"), "").unwrap();
+        let archive_path = dir.join("archive.zip");
+        // Removing the non-empty directory `sub` fails.
+        let deletion_entries = ["archive.zip", "a.txt", "sub"]
+            .iter()
+            .map(|file_name| DeletionEntry {
+                path: dir.join(file_name),
+                entry: FileEntry::default(),
+                archive_path: archive_path.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let plan = build_deletion_plan(
+            deletion_entries,
+            DeletionConfig {
+                ..Default::default()
+            },
+        );
+        let stats = execute_plan(&plan).unwrap();
+        let archive_exists = archive_path.exists();
+        let a_exists = dir.join("a.txt").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(1, stats.files);
+        assert_eq!(1, stats.failed);
+        assert!(archive_exists);
+        assert!(!a_exists);
+    }
+
+    #[test]
+    fn simulate_removing_empty_directories() {
+        let dir = std::env::temp_dir().join("rmext_simulate_removing_empty_directories");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("d/e")).unwrap();
+        fs::write(dir.join("d/e/a.txt"), "").unwrap();
+        let deletion_entries = vec![DeletionEntry {
+            path: dir.join("d/e/a.txt"),
+            entry: FileEntry::default(),
+            archive_path: dir.join("archive.zip"),
+        }];
+
+        let remover = new_remover(true);
+        remove_file(&*remover, &deletion_entries[0].path).unwrap();
+        // As with --no-recursive-warn.
+        let mut out = Vec::new();
+        let removed =
+            remove_empty_directories(&*remover, &deletion_entries, None, false, &mut out).unwrap();
+
+        let dirs_exist = dir.join("d/e/a.txt").exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(2, removed);
+        assert!(dirs_exist);
+        assert!(!String::from_utf8(out)
+            .unwrap()
+            .contains("Remove empty directory recursively."));
+    }
+
+    #[test]
+    fn search_paths_without_parent_or_stem() {
+        assert_eq!(
+            vec![PathBuf::from(""), PathBuf::from("archive")],
+            search_paths(Path::new("archive.zip"), Mode::Both)
+        );
+        assert_eq!(
+            vec![PathBuf::from(".")],
+            search_paths(Path::new("/"), Mode::Both)
+        );
     }
 }
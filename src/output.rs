@@ -0,0 +1,262 @@
+pub mod histogram;
+pub mod tree;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::archive::FileEntry;
+
+/// Group name of files at the archive root.
+pub const ROOT_GROUP: &str = "(root)";
+
+/// Group name of files without an extension.
+pub const NO_EXTENSION_GROUP: &str = "(none)";
+
+/// Suffix of the split output listing files.
+pub const SPLIT_OUTPUT_FILES_SUFFIX: &str = ".files";
+
+/// Suffix of the split output listing directories.
+pub const SPLIT_OUTPUT_DIRS_SUFFIX: &str = ".dirs";
+
+/// Encode text for the console. Only takes effect on Windows.
+pub fn encode_output(text: &str, output_enc: &str) -> Vec<u8> {
+    match output_enc {
+        "cp932" if cfg!(windows) => encoding_rs::SHIFT_JIS.encode(text).0.into_owned(),
+        _ => text.as_bytes().to_vec(),
+    }
+}
+
+/// Print a line to stdout in `output_enc`.
+pub fn print_line(line: &str, output_enc: &str) {
+    let _ = write_line(&mut io::stdout(), line, output_enc);
+}
+
+/// Write a line to `out` in `output_enc`.
+pub fn write_line(out: &mut dyn Write, line: &str, output_enc: &str) -> io::Result<()> {
+    let mut bytes = encode_output(line, output_enc);
+    bytes.push(b'\n');
+    out.write_all(&bytes)
+}
+
+/// Write `contents` to a temporary file next to `path` and rename it to `path`,
+/// so that `path` is never left half-written.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_file_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_file_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_file_name);
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()
+    })();
+    match result {
+        Ok(()) => fs::rename(&temp_path, path),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Write files to `<output_file>.files` and directories to `<output_file>.dirs`,
+/// each path terminated by `delimiter`. Returns the paths of the two files.
+pub fn write_split_output(
+    output_file: &Path,
+    paths: &[(PathBuf, bool)],
+    delimiter: u8,
+    output_enc: &str,
+) -> io::Result<(PathBuf, PathBuf)> {
+    let with_suffix = |suffix: &str| {
+        let mut path = output_file.as_os_str().to_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    };
+    let files_path = with_suffix(SPLIT_OUTPUT_FILES_SUFFIX);
+    let dirs_path = with_suffix(SPLIT_OUTPUT_DIRS_SUFFIX);
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for (path, is_directory) in paths {
+        let out = if *is_directory { &mut dirs } else { &mut files };
+        out.extend(encode_output(&path.to_string_lossy(), output_enc));
+        out.push(delimiter);
+    }
+    write_atomically(&files_path, &files)?;
+    write_atomically(&dirs_path, &dirs)?;
+    Ok((files_path, dirs_path))
+}
+
+/// Print a path of the list to stdout in `output_enc`.
+pub fn print_path(path: &Path, output_enc: &str) {
+    print_line(&format!("\t{}", path.to_string_lossy()), output_enc);
+}
+
+/// Count files and total size by the top-level directory.
+/// Files at the archive root are grouped into `(root)`.
+pub fn group_by_top_dir(entries: &[FileEntry]) -> HashMap<String, (u64, u64)> {
+    let mut groups: HashMap<String, (u64, u64)> = HashMap::new();
+    for entry in entries.iter().filter(|entry| !entry.is_directory) {
+        let mut components = entry
+            .file_name
+            .split(['/', '\\'])
+            .filter(|component| !matches!(*component, "" | "." | ".."));
+        let first = components.next().unwrap_or_default();
+        let group = if components.next().is_some() {
+            format!("{}/", first)
+        } else {
+            ROOT_GROUP.to_string()
+        };
+        let (count, size) = groups.entry(group).or_default();
+        *count += 1;
+        *size += entry.uncompressed_size;
+    }
+    groups
+}
+
+/// Count removed files and freed bytes by the file extension.
+/// Files without an extension are grouped into `(none)`.
+pub fn extension_report(stats: &[(PathBuf, u64)]) -> BTreeMap<String, (u64, u64)> {
+    let mut groups: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for (path, size) in stats {
+        let group = match path.extension() {
+            Some(extension) => format!(".{}", extension.to_string_lossy()),
+            None => NO_EXTENSION_GROUP.to_string(),
+        };
+        let (count, bytes) = groups.entry(group).or_default();
+        *count += 1;
+        *bytes += size;
+    }
+    groups
+}
+
+/// Format size in bytes with binary units.
+/// e.g.) `1258291 => 1.2 MiB`
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{:.1} {}", value, UNITS[unit])
+    } else {
+        format!("{:.0} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        encode_output, extension_report, format_size, group_by_top_dir, write_atomically,
+        write_line, write_split_output,
+    };
+    use crate::archive::FileEntry;
+    use std::collections::{BTreeMap, HashMap};
+    use std::path::PathBuf;
+
+    #[test]
+    fn group_entries_by_top_dir() {
+        let entry = |file_name: &str, uncompressed_size, is_directory| FileEntry {
+            file_name: file_name.to_string(),
+            uncompressed_size,
+            is_directory,
+            ..Default::default()
+        };
+        let entries = vec![
+            entry("src/", 0, true),
+            entry("src/main.rs", 100, false),
+            entry("src/archive/zip.rs", 20, false),
+            entry("./tests/a.rs", 3, false),
+            entry("README.md", 7, false),
+        ];
+        assert_eq!(
+            HashMap::from([
+                ("src/".to_string(), (2, 120)),
+                ("tests/".to_string(), (1, 3)),
+                ("(root)".to_string(), (1, 7)),
+            ]),
+            group_by_top_dir(&entries)
+        );
+    }
+
+    #[test]
+    fn group_removed_files_by_extension() {
+        let stats = vec![
+            (PathBuf::from("a/b.txt"), 10),
+            (PathBuf::from("c.txt"), 5),
+            (PathBuf::from("d.tar.gz"), 100),
+            (PathBuf::from("Makefile"), 1),
+        ];
+        assert_eq!(
+            BTreeMap::from([
+                (".txt".to_string(), (2, 15)),
+                (".gz".to_string(), (1, 100)),
+                ("(none)".to_string(), (1, 1)),
+            ]),
+            extension_report(&stats)
+        );
+    }
+
+    #[test]
+    fn write_lines_to_file_atomically() {
+        let path = std::env::temp_dir().join("rmext_write_lines_to_file_atomically.txt");
+        std::fs::write(&path, "old contents which are longer").unwrap();
+        let mut buf = Vec::new();
+        write_line(&mut buf, "a.txt", "utf8").unwrap();
+        write_line(&mut buf, "b.txt", "utf8").unwrap();
+        write_atomically(&path, &buf).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!("a.txt\nb.txt\n", contents);
+    }
+
+    #[test]
+    fn split_output_into_files_and_dirs() {
+        let output_file = std::env::temp_dir().join("rmext_split_output_into_files_and_dirs.txt");
+        let paths = [
+            (PathBuf::from("a/"), true),
+            (PathBuf::from("a/b.txt"), false),
+            (PathBuf::from("a/c/"), true),
+            (PathBuf::from("d.txt"), false),
+        ];
+        let (files_path, dirs_path) =
+            write_split_output(&output_file, &paths, b'\0', "utf8").unwrap();
+        let files = std::fs::read(&files_path).unwrap();
+        let dirs = std::fs::read(&dirs_path).unwrap();
+        std::fs::remove_file(&files_path).unwrap();
+        std::fs::remove_file(&dirs_path).unwrap();
+        assert_eq!(
+            "rmext_split_output_into_files_and_dirs.txt.files",
+            files_path.file_name().unwrap()
+        );
+        assert_eq!(b"a/b.txt\0d.txt\0".to_vec(), files);
+        assert_eq!(b"a/\0a/c/\0".to_vec(), dirs);
+        assert!(!output_file.exists());
+    }
+
+    #[test]
+    fn encode_output_for_console() {
+        assert_eq!("テキスト".as_bytes(), encode_output("テキスト", "utf8"));
+        if cfg!(windows) {
+            assert_eq!(
+                b"\x83e\x83L\x83X\x83g".to_vec(),
+                encode_output("テキスト", "cp932")
+            );
+        } else {
+            assert_eq!("テキスト".as_bytes(), encode_output("テキスト", "cp932"));
+        }
+    }
+
+    #[test]
+    fn format_size_with_binary_units() {
+        assert_eq!("512 B", format_size(512));
+        assert_eq!("300 KiB", format_size(300 * 1024));
+        assert_eq!("1.2 MiB", format_size(1258291));
+    }
+}
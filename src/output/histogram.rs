@@ -0,0 +1,81 @@
+use crate::archive::FileEntry;
+
+/// Upper bounds (exclusive) and names of the size buckets.
+const BUCKETS: [(u64, &str); 4] = [
+    (1024, "<1KB"),
+    (10 * 1024, "1KB-10KB"),
+    (100 * 1024, "10KB-100KB"),
+    (1024 * 1024, "100KB-1MB"),
+];
+
+/// Name of the bucket of files larger than all the buckets.
+const LARGEST_BUCKET: &str = ">1MB";
+
+/// Count files by the uncompressed size in logarithmic buckets. Directories are not counted.
+/// Every bucket is returned in ascending order of size even if no files fall into it.
+pub fn size_histogram(entries: &[FileEntry]) -> Vec<(String, u64)> {
+    let mut counts = [0u64; BUCKETS.len() + 1];
+    for entry in entries.iter().filter(|entry| !entry.is_directory) {
+        let bucket = BUCKETS
+            .iter()
+            .position(|(upper, _)| entry.uncompressed_size < *upper)
+            .unwrap_or(BUCKETS.len());
+        counts[bucket] += 1;
+    }
+    BUCKETS
+        .iter()
+        .map(|(_, name)| *name)
+        .chain([LARGEST_BUCKET])
+        .zip(counts)
+        .map(|(name, count)| (name.to_string(), count))
+        .collect()
+}
+
+/// Format the non-empty buckets in a line.
+/// e.g.) `<1KB: 234 files | 1KB-10KB: 89 files | >1MB: 4 files`
+pub fn format_histogram(histogram: &[(String, u64)]) -> String {
+    histogram
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(bucket, count)| format!("{}: {} files", bucket, count))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_histogram, size_histogram};
+    use crate::archive::FileEntry;
+
+    #[test]
+    fn count_files_by_size_bucket() {
+        let entry = |uncompressed_size, is_directory| FileEntry {
+            uncompressed_size,
+            is_directory,
+            ..Default::default()
+        };
+        let entries = vec![
+            entry(0, true),
+            entry(10, false),
+            entry(1023, false),
+            entry(1024, false),
+            entry(200 * 1024, false),
+            entry(5 * 1024 * 1024, false),
+        ];
+        let histogram = size_histogram(&entries);
+        assert_eq!(
+            vec![
+                ("<1KB".to_string(), 2),
+                ("1KB-10KB".to_string(), 1),
+                ("10KB-100KB".to_string(), 0),
+                ("100KB-1MB".to_string(), 1),
+                (">1MB".to_string(), 1),
+            ],
+            histogram
+        );
+        assert_eq!(
+            "<1KB: 2 files | 1KB-10KB: 1 files | 100KB-1MB: 1 files | >1MB: 1 files",
+            format_histogram(&histogram)
+        );
+    }
+}
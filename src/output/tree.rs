@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory or file in the tree. Nodes with children are directories.
+#[derive(Default)]
+struct TreeNode {
+    children: BTreeMap<String, TreeNode>,
+}
+
+impl TreeNode {
+    fn insert(&mut self, names: impl Iterator<Item = String>) {
+        let mut node = self;
+        for name in names {
+            node = node.children.entry(name).or_default();
+        }
+    }
+
+    fn render(&self, indent: &str, out: &mut String) {
+        let last = self.children.len().saturating_sub(1);
+        for (i, (name, child)) in self.children.iter().enumerate() {
+            let (branch, child_indent) = if i == last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            out.push_str(indent);
+            out.push_str(branch);
+            out.push_str(name);
+            if !child.children.is_empty() {
+                out.push('/');
+            }
+            out.push('\n');
+            child.render(&format!("{}{}", indent, child_indent), out);
+        }
+    }
+}
+
+/// Render paths as a directory tree like the `tree` command, relative to `base`.
+/// e.g.)
+/// ```text
+/// archive/
+/// ├── src/
+/// │   └── main.rs
+/// └── Cargo.toml
+/// ```
+pub fn render_tree(paths: &[PathBuf], base: &Path) -> String {
+    let mut root = TreeNode::default();
+    for path in paths {
+        let relative_path = path.strip_prefix(base).unwrap_or(path);
+        root.insert(
+            relative_path
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                    _ => None,
+                }),
+        );
+    }
+    let base_name = base
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| ".".to_string());
+    let mut out = format!("{}/\n", base_name);
+    root.render("", &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_tree;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn render_paths_as_tree() {
+        let base = Path::new("downloads/archive");
+        let paths = [
+            "src/main.rs",
+            "Cargo.toml",
+            "src/archive/zip.rs",
+            "src/lib.rs",
+        ]
+        .map(|path| base.join(path))
+        .to_vec();
+        assert_eq!(
+            "archive/
+├── Cargo.toml
+└── src/
+    ├── archive/
+    │   └── zip.rs
+    ├── lib.rs
+    └── main.rs
+",
+            render_tree(&paths, base)
+        );
+        assert_eq!("archive/\n", render_tree(&Vec::<PathBuf>::new(), base));
+    }
+}
@@ -0,0 +1,85 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+/// Removes files and directories. Lets the deletion loop run without touching the filesystem.
+pub trait Remover {
+    fn remove_file(&self, p: &Path) -> Result<()>;
+    fn remove_dir(&self, p: &Path) -> Result<()>;
+
+    /// Whether the directory has no entries left after the removals so far.
+    fn is_empty_dir(&self, p: &Path) -> Result<bool> {
+        Ok(p.read_dir()?.next().is_none())
+    }
+}
+
+/// Calls `fs::remove_file` and `fs::remove_dir`.
+pub struct RealRemover;
+
+impl Remover for RealRemover {
+    fn remove_file(&self, p: &Path) -> Result<()> {
+        fs::remove_file(p)
+    }
+
+    fn remove_dir(&self, p: &Path) -> Result<()> {
+        fs::remove_dir(p)
+    }
+}
+
+/// Prints what would be removed and succeeds without removing it.
+/// The paths are recorded so that their directories are regarded as empty.
+#[derive(Default)]
+pub struct SimulatedRemover {
+    removed: RefCell<HashSet<PathBuf>>,
+}
+
+impl Remover for SimulatedRemover {
+    fn remove_file(&self, p: &Path) -> Result<()> {
+        println!("\tSIMULATED remove_file: {}", p.to_string_lossy());
+        self.removed.borrow_mut().insert(p.to_path_buf());
+        Ok(())
+    }
+
+    fn remove_dir(&self, p: &Path) -> Result<()> {
+        println!("\tSIMULATED remove_dir: {}", p.to_string_lossy());
+        self.removed.borrow_mut().insert(p.to_path_buf());
+        Ok(())
+    }
+
+    fn is_empty_dir(&self, p: &Path) -> Result<bool> {
+        let removed = self.removed.borrow();
+        for entry in p.read_dir()? {
+            if !removed.contains(&entry?.path()) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RealRemover, Remover, SimulatedRemover};
+    use std::fs;
+
+    #[test]
+    fn simulated_remover_keeps_files() {
+        let dir = std::env::temp_dir().join("rmext_simulated_remover_keeps_files");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "").unwrap();
+        let simulated = SimulatedRemover::default();
+        let empty_before = simulated.is_empty_dir(&dir).unwrap();
+        simulated.remove_file(&dir.join("a.txt")).unwrap();
+        let empty_after = simulated.is_empty_dir(&dir).unwrap();
+        simulated.remove_dir(&dir).unwrap();
+        let simulated_exists = dir.join("a.txt").exists();
+        RealRemover.remove_file(&dir.join("a.txt")).unwrap();
+        RealRemover.remove_dir(&dir).unwrap();
+        assert!(!empty_before);
+        assert!(empty_after);
+        assert!(simulated_exists);
+        assert!(!dir.exists());
+    }
+}
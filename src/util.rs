@@ -0,0 +1,7 @@
+pub mod dedup;
+pub mod digest;
+pub mod disk;
+pub mod display;
+pub mod path;
+pub mod sandbox;
+pub mod time;
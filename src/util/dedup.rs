@@ -0,0 +1,51 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Group paths by file name (the last component), keeping only the names found
+/// in more than one parent directory. e.g.) an archive extracted twice into different directories.
+pub fn find_duplicate_names(paths: &[PathBuf]) -> HashMap<String, Vec<&PathBuf>> {
+    let mut by_name: HashMap<String, Vec<&PathBuf>> = HashMap::new();
+    for path in paths {
+        if let Some(file_name) = path.file_name() {
+            by_name
+                .entry(file_name.to_string_lossy().into_owned())
+                .or_default()
+                .push(path);
+        }
+    }
+    by_name.retain(|_, paths| {
+        paths
+            .iter()
+            .map(|path| path.parent())
+            .collect::<HashSet<_>>()
+            .len()
+            > 1
+    });
+    by_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_duplicate_names;
+    use std::path::PathBuf;
+
+    #[test]
+    fn find_names_in_multiple_directories() {
+        let paths = vec![
+            PathBuf::from("a/readme.txt"),
+            PathBuf::from("a/data.bin"),
+            PathBuf::from("b/readme.txt"),
+            PathBuf::from("b/other.txt"),
+            PathBuf::from("b/other.txt"),
+        ];
+        let duplicates = find_duplicate_names(&paths);
+        assert_eq!(1, duplicates.len());
+        assert_eq!(
+            vec![
+                &PathBuf::from("a/readme.txt"),
+                &PathBuf::from("b/readme.txt")
+            ],
+            duplicates["readme.txt"]
+        );
+    }
+}
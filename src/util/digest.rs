@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Parse a checksum file written by `sha256sum`.
+/// Both GNU (`<hash>  <path>`, `<hash> *<path>`) and BSD (`SHA256 (<path>) = <hash>`) formats are supported.
+/// Relative paths are made absolute against the current directory.
+pub fn parse_digest_file(path: &Path) -> Result<HashMap<PathBuf, [u8; 32]>> {
+    let mut digests = HashMap::new();
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (hash, file_path) = parse_digest_line(line).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid digest line {}: {}", i + 1, line),
+            )
+        })?;
+        digests.insert(std::path::absolute(file_path)?, hash);
+    }
+    Ok(digests)
+}
+
+fn parse_digest_line(line: &str) -> Option<([u8; 32], &str)> {
+    if let Some(rest) = line.strip_prefix("SHA256 (") {
+        let (file_path, hash) = rest.rsplit_once(") = ")?;
+        return Some((decode_hex(hash)?, file_path));
+    }
+    let (hash, file_path) = line.split_once(' ')?;
+    let file_path = file_path
+        .strip_prefix(' ')
+        .or_else(|| file_path.strip_prefix('*'))?;
+    Some((decode_hex(hash)?, file_path))
+}
+
+fn decode_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// Compute SHA-256 of a file.
+pub fn sha256_file(path: &Path) -> Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// Write SHA-256 of the files in the `sha256sum` format (`<hash>  <absolute path>`).
+/// The lines are written to a temporary file which is renamed to `out` at last.
+pub fn compute_and_write_hashes(paths: &[PathBuf], out: &Path) -> Result<()> {
+    let mut temp_file_name = out.file_name().unwrap_or_default().to_os_string();
+    temp_file_name.push(".tmp");
+    let temp_path = out.with_file_name(temp_file_name);
+
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        for path in paths {
+            writeln!(
+                writer,
+                "{}  {}",
+                encode_hex(&sha256_file(path)?),
+                std::path::absolute(path)?.to_string_lossy()
+            )?;
+        }
+        writer.into_inner()?.sync_all()
+    })();
+    match result {
+        Ok(()) => fs::rename(&temp_path, out),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Compute CRC-32 of a file.
+pub fn crc32_file(path: &Path) -> Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        match file.read(&mut buf)? {
+            0 => return Ok(hasher.finalize()),
+            n => hasher.update(&buf[..n]),
+        }
+    }
+}
+
+/// Find a file with CRC-32 `crc` in `dir`.
+/// Candidates are the file named `name` and files with the same file stem, e.g.) `a.txt` and `a.bak`.
+/// The file named `name` is tried first.
+pub fn find_file_by_crc(crc: u32, dir: &Path, name: &str) -> Option<PathBuf> {
+    let stem = Path::new(name).file_stem()?;
+    let named_path = dir.join(name);
+    // `dir` is empty for archives in the current directory given without a directory.
+    let read_dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut candidates: Vec<PathBuf> = fs::read_dir(read_dir)
+        .ok()?
+        .filter_map(|dir_entry| dir_entry.ok())
+        .map(|dir_entry| dir.join(dir_entry.file_name()))
+        .filter(|path| *path != named_path && path.file_stem() == Some(stem))
+        .collect();
+    candidates.sort();
+    std::iter::once(named_path)
+        .chain(candidates)
+        .filter(|path| path.is_file())
+        .find(|path| crc32_file(path).is_ok_and(|crc32| crc32 == crc))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedCrc {
+    modified: SystemTime,
+    size: u64,
+    crc32: u32,
+}
+
+/// CRC-32 of files on disk persisted in a JSON file between runs.
+/// A cached value is used while the modified time and the size of the file are unchanged.
+pub struct CrcCache {
+    path: PathBuf,
+    entries: HashMap<PathBuf, CachedCrc>,
+}
+
+impl CrcCache {
+    /// Load the cache file. The cache is empty if the file does not exist.
+    pub fn load(path: &Path) -> Result<CrcCache> {
+        let entries = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(CrcCache {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Compute CRC-32 of a file unless it is cached.
+    pub fn crc32(&mut self, path: &Path) -> Result<u32> {
+        let path = std::path::absolute(path)?;
+        let metadata = fs::metadata(&path)?;
+        let modified = metadata.modified()?;
+        let size = metadata.len();
+        if let Some(cached) = self.entries.get(&path) {
+            if cached.modified == modified && cached.size == size {
+                return Ok(cached.crc32);
+            }
+        }
+        let crc32 = crc32_file(&path)?;
+        self.entries.insert(
+            path,
+            CachedCrc {
+                modified,
+                size,
+                crc32,
+            },
+        );
+        Ok(crc32)
+    }
+
+    /// Remove entries of files which no longer exist and write the cache file.
+    pub fn save(&mut self) -> Result<()> {
+        self.entries.retain(|path, _| path.is_file());
+        fs::write(&self.path, serde_json::to_string(&self.entries)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compute_and_write_hashes, crc32_file, find_file_by_crc, parse_digest_file, sha256_file,
+        CrcCache,
+    };
+    use std::fs;
+
+    #[test]
+    fn parse_gnu_and_bsd_digest_lines() {
+        let dir = std::env::temp_dir().join("rmext_parse_gnu_and_bsd_digest_lines");
+        fs::create_dir_all(&dir).unwrap();
+        let empty_file = dir.join("empty.txt");
+        fs::write(&empty_file, "").unwrap();
+        let empty_hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let digest_file = dir.join("checksums.sha256");
+        fs::write(
+            &digest_file,
+            format!(
+                "{0}  {1}\n{0} *{2}\nSHA256 ({3}) = {0}\n",
+                empty_hash,
+                empty_file.to_string_lossy(),
+                dir.join("binary.bin").to_string_lossy(),
+                dir.join("bsd (1).txt").to_string_lossy()
+            ),
+        )
+        .unwrap();
+
+        let digests = parse_digest_file(&digest_file).unwrap();
+        let hash = sha256_file(&empty_file).unwrap();
+        let crc32 = crc32_file(&empty_file).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(0, crc32);
+        assert_eq!(3, digests.len());
+        assert_eq!(Some(&hash), digests.get(&empty_file));
+        assert_eq!(Some(&hash), digests.get(&dir.join("bsd (1).txt")));
+        assert!(digests.contains_key(&dir.join("binary.bin")));
+    }
+
+    #[test]
+    fn write_hashes_readable_by_parser() {
+        let dir = std::env::temp_dir().join("rmext_write_hashes_readable_by_parser");
+        fs::create_dir_all(&dir).unwrap();
+        let paths = vec![dir.join("a.txt"), dir.join("b b.txt")];
+        fs::write(&paths[0], "a").unwrap();
+        fs::write(&paths[1], "").unwrap();
+        let hash_file = dir.join("hashes.sha256");
+
+        compute_and_write_hashes(&paths, &hash_file).unwrap();
+        let content = fs::read_to_string(&hash_file).unwrap();
+        let digests = parse_digest_file(&hash_file).unwrap();
+        let temp_file_exists = dir.join("hashes.sha256.tmp").exists();
+        let missing = compute_and_write_hashes(&[dir.join("missing.txt")], &hash_file);
+        let hash = sha256_file(&paths[0]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(content
+            .starts_with("ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb  "));
+        assert_eq!(Some(&hash), digests.get(&paths[0]));
+        assert_eq!(2, digests.len());
+        assert!(!temp_file_exists);
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn reuse_cached_crc_while_file_is_unchanged() {
+        let dir = std::env::temp_dir().join("rmext_reuse_cached_crc_while_file_is_unchanged");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.txt");
+        let removed_file = dir.join("b.txt");
+        fs::write(&file, "abc").unwrap();
+        fs::write(&removed_file, "").unwrap();
+        let cache_path = dir.join("archive.zip.rmext_crc_cache");
+
+        let mut cache = CrcCache::load(&cache_path).unwrap();
+        let crc32 = cache.crc32(&file).unwrap();
+        cache.crc32(&removed_file).unwrap();
+        fs::remove_file(&removed_file).unwrap();
+        cache.save().unwrap();
+
+        let mut cache = CrcCache::load(&cache_path).unwrap();
+        let entries = cache.entries.len();
+        let cached = cache.entries.get_mut(&file).unwrap();
+        // A cached value is trusted while the file looks unchanged.
+        cached.crc32 = 0;
+        let stale = cache.crc32(&file).unwrap();
+        fs::write(&file, "abcd").unwrap();
+        let recomputed = cache.crc32(&file).unwrap();
+        let expected = crc32_file(&file).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(0x352441c2, crc32);
+        assert_eq!(1, entries);
+        assert_eq!(0, stale);
+        assert_eq!(expected, recomputed);
+    }
+
+    #[test]
+    fn find_renamed_file_by_crc() {
+        let dir = std::env::temp_dir().join("rmext_find_renamed_file_by_crc");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "abd").unwrap();
+        fs::write(dir.join("a.bak"), "abc").unwrap();
+        fs::write(dir.join("b.txt"), "abc").unwrap();
+
+        let renamed = find_file_by_crc(0x352441c2, &dir, "a.txt");
+        let named = find_file_by_crc(0x352441c2, &dir, "b.txt");
+        let not_found = find_file_by_crc(0x352441c2, &dir, "c.txt");
+        let missing_dir = find_file_by_crc(0x352441c2, &dir.join("missing"), "a.txt");
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(Some(dir.join("a.bak")), renamed);
+        assert_eq!(Some(dir.join("b.txt")), named);
+        assert_eq!(None, not_found);
+        assert_eq!(None, missing_dir);
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SpaceError {
+    Insufficient { required: u64, available: u64 },
+    Io(io::Error),
+}
+
+impl fmt::Display for SpaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpaceError::Insufficient {
+                required,
+                available,
+            } => write!(
+                f,
+                "insufficient disk space: {} bytes required, {} bytes available",
+                required, available
+            ),
+            SpaceError::Io(e) => write!(f, "failed to get free disk space: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SpaceError {}
+
+/// Check that the filesystem containing `path` has at least `required` bytes free.
+pub fn check_disk_space(required: u64, path: &Path) -> Result<(), SpaceError> {
+    let available = fs2::free_space(path).map_err(SpaceError::Io)?;
+    if available < required {
+        return Err(SpaceError::Insufficient {
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_disk_space, SpaceError};
+
+    #[test]
+    fn report_insufficient_space() {
+        let dir = std::env::temp_dir();
+        assert!(check_disk_space(0, &dir).is_ok());
+        assert!(matches!(
+            check_disk_space(u64::MAX, &dir),
+            Err(SpaceError::Insufficient { .. })
+        ));
+    }
+}
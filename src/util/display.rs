@@ -0,0 +1,51 @@
+use std::path::{Path, MAIN_SEPARATOR};
+
+/// Truncate path to at most `max_chars` characters for display.
+/// Leading components are replaced with `...` so that the trailing ones are kept.
+/// The last component is always kept even if it is longer than `max_chars`.
+/// e.g.) `a/b/c/d.txt => .../c/d.txt`
+pub fn truncate_path_display(path: &Path, max_chars: usize) -> String {
+    let display = path.to_string_lossy();
+    if display.chars().count() <= max_chars {
+        return display.into_owned();
+    }
+    let components: Vec<String> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let prefix = format!("...{}", MAIN_SEPARATOR);
+    let mut truncated = components.last().cloned().unwrap_or_default();
+    // The first component is never kept since at least it is replaced with `...`.
+    for component in components
+        .iter()
+        .rev()
+        .skip(1)
+        .take(components.len().saturating_sub(2))
+    {
+        let candidate = format!("{}{}{}", component, MAIN_SEPARATOR, truncated);
+        if prefix.chars().count() + candidate.chars().count() > max_chars {
+            break;
+        }
+        truncated = candidate;
+    }
+    format!("{}{}", prefix, truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::truncate_path_display;
+    use std::path::{Path, MAIN_SEPARATOR};
+
+    #[test]
+    fn truncate_at_separator() {
+        let path = Path::new("directory")
+            .join("sub")
+            .join("フォルダー")
+            .join("テキスト.txt");
+        assert_eq!(path.to_string_lossy(), truncate_path_display(&path, 100));
+        let expected = format!("...{0}フォルダー{0}テキスト.txt", MAIN_SEPARATOR);
+        assert_eq!(expected, truncate_path_display(&path, 18));
+        let expected = format!("...{}テキスト.txt", MAIN_SEPARATOR);
+        assert_eq!(expected, truncate_path_display(&path, 5));
+    }
+}
@@ -0,0 +1,108 @@
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory containing the archive. `.` if the path has no parent, e.g.) `/`.
+/// A bare file name has the empty path as its parent, which also means the current directory.
+pub fn archive_dir(archive_path: &Path) -> &Path {
+    archive_path.parent().unwrap_or_else(|| Path::new("."))
+}
+
+/// Stem of the archive file name, or `None` if it has no file name or the stem is empty.
+/// e.g.) `archive.zip => archive`, `archive. => archive`, `.. => None`
+pub fn archive_stem(archive_path: &Path) -> Option<&OsStr> {
+    archive_path.file_stem().filter(|stem| !stem.is_empty())
+}
+
+/// Path relative to the current directory for messages.
+/// Paths outside the current directory are returned as is.
+/// e.g.) `/home/user/downloads/archive.zip => archive.zip` in `/home/user/downloads`
+pub fn archive_path_relative_to_cwd(path: &Path) -> PathBuf {
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    path.strip_prefix(current_dir).unwrap_or(path).to_path_buf()
+}
+
+/// Whether any component of the path starts with `.`.
+/// e.g.) `.git/config`, `a/.env`
+pub fn is_hidden_path(path: &Path) -> bool {
+    path.components().any(|component| match component {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    })
+}
+
+/// Whether any component of the path is one of `names`.
+/// e.g.) `node_modules/a/package.json` with `["node_modules"]`
+pub fn is_within_ignored_dir(path: &Path, names: &[&str]) -> bool {
+    path.components().any(|component| match component {
+        Component::Normal(name) => names.iter().any(|ignored| name == *ignored),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        archive_dir, archive_path_relative_to_cwd, archive_stem, is_hidden_path,
+        is_within_ignored_dir,
+    };
+    use std::ffi::OsStr;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn archive_dir_and_stem_without_parent_or_stem() {
+        assert_eq!(Path::new(""), archive_dir(Path::new("archive.zip")));
+        assert_eq!(Path::new("/"), archive_dir(Path::new("/archive.zip")));
+        assert_eq!(Path::new("."), archive_dir(Path::new("/")));
+        assert_eq!(
+            Some(OsStr::new("archive")),
+            archive_stem(Path::new("a/archive.zip"))
+        );
+        assert_eq!(
+            Some(OsStr::new("archive")),
+            archive_stem(Path::new("archive."))
+        );
+        assert_eq!(None, archive_stem(Path::new("a/..")));
+        assert_eq!(None, archive_stem(Path::new("/")));
+    }
+
+    #[test]
+    fn strip_current_dir() {
+        let current_dir = std::env::current_dir().unwrap();
+        assert_eq!(
+            PathBuf::from("archive/a.txt"),
+            archive_path_relative_to_cwd(&current_dir.join("archive/a.txt"))
+        );
+        let outside = current_dir.parent().unwrap().join("other/archive.zip");
+        assert_eq!(outside, archive_path_relative_to_cwd(&outside));
+        assert_eq!(
+            PathBuf::from("archive.zip"),
+            archive_path_relative_to_cwd(&PathBuf::from("archive.zip"))
+        );
+    }
+
+    #[test]
+    fn hidden_path_components() {
+        assert!(is_hidden_path(Path::new(".gitignore")));
+        assert!(is_hidden_path(Path::new("a/.git/config")));
+        assert!(is_hidden_path(Path::new("a/.DS_Store")));
+        assert!(!is_hidden_path(Path::new("a/b.txt")));
+        assert!(!is_hidden_path(Path::new("./a/../b.txt")));
+    }
+
+    #[test]
+    fn path_within_ignored_dir() {
+        let names = ["__pycache__", "node_modules"];
+        assert!(is_within_ignored_dir(
+            Path::new("node_modules/a/package.json"),
+            &names
+        ));
+        assert!(is_within_ignored_dir(
+            Path::new("src/__pycache__/a.pyc"),
+            &names
+        ));
+        assert!(!is_within_ignored_dir(
+            Path::new("src/node_modules.txt"),
+            &names
+        ));
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// A path outside the directory given to `--sandbox`.
+#[derive(Debug)]
+pub struct SandboxViolation {
+    pub path: PathBuf,
+    pub sandbox: PathBuf,
+}
+
+impl fmt::Display for SandboxViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sandbox violation: {} is outside {}",
+            self.path.to_string_lossy(),
+            self.sandbox.to_string_lossy()
+        )
+    }
+}
+
+impl std::error::Error for SandboxViolation {}
+
+/// Whether the path is within `sandbox` after resolving symbolic links.
+/// A path which does not exist yet is resolved from its nearest existing ancestor.
+pub fn is_in_sandbox(path: &Path, sandbox: &Path) -> bool {
+    match (resolve(path), sandbox.canonicalize()) {
+        (Some(path), Ok(sandbox)) => path.starts_with(sandbox),
+        _ => false,
+    }
+}
+
+/// `Err` if the path is outside `sandbox`.
+pub fn check_sandbox(path: &Path, sandbox: &Path) -> Result<(), SandboxViolation> {
+    if is_in_sandbox(path, sandbox) {
+        Ok(())
+    } else {
+        Err(SandboxViolation {
+            path: path.to_path_buf(),
+            sandbox: sandbox.to_path_buf(),
+        })
+    }
+}
+
+/// Canonicalize the nearest existing ancestor and append the rest of the path.
+/// `None` if the rest contains `..`, which cannot be resolved without the directories.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    let path = std::path::absolute(path).ok()?;
+    for ancestor in path.ancestors() {
+        if let Ok(resolved) = ancestor.canonicalize() {
+            let rest = path.strip_prefix(ancestor).ok()?;
+            if rest
+                .components()
+                .any(|component| component == Component::ParentDir)
+            {
+                return None;
+            }
+            return Some(resolved.join(rest));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_sandbox, is_in_sandbox};
+    use std::fs;
+
+    #[test]
+    fn paths_inside_and_outside_sandbox() {
+        let dir = std::env::temp_dir().join("rmext_paths_inside_and_outside_sandbox");
+        let sandbox = dir.join("sandbox");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(sandbox.join("sub")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        fs::write(sandbox.join("sub/a.txt"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.join("outside"), sandbox.join("link")).unwrap();
+
+        assert!(is_in_sandbox(&sandbox.join("sub/a.txt"), &sandbox));
+        assert!(is_in_sandbox(&sandbox.join("new/backup"), &sandbox));
+        assert!(!is_in_sandbox(&sandbox.join("sub/../../outside"), &sandbox));
+        assert!(!is_in_sandbox(&sandbox.join("new/../../outside"), &sandbox));
+        assert!(!is_in_sandbox(&dir.join("outside"), &sandbox));
+        #[cfg(unix)]
+        assert!(!is_in_sandbox(&sandbox.join("link/a.txt"), &sandbox));
+        let violation = check_sandbox(&dir.join("outside"), &sandbox).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(violation.to_string().contains("outside"));
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Seconds between 1601-01-01 (Windows FILETIME epoch) and 1970-01-01.
+const FILETIME_UNIX_EPOCH_DIFF_SECS: u64 = 11_644_473_600;
+
+/// Format timestamp as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn format_timestamp(ts: &SystemTime) -> String {
+    let secs = match ts.duration_since(UNIX_EPOCH) {
+        Ok(v) => v.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let days = secs.div_euclid(86400);
+    let secs_of_day = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Convert MS-DOS date and time fields. Zeroed fields are treated as unavailable.
+pub fn from_dos_date_time(date: u16, time: u16) -> Option<SystemTime> {
+    let year = i64::from(date >> 9) + 1980;
+    let month = u32::from((date >> 5) & 0x0f);
+    let day = u32::from(date & 0x1f);
+    let hour = u64::from(time >> 11);
+    let minute = u64::from((time >> 5) & 0x3f);
+    let second = u64::from(time & 0x1f) * 2;
+    if !(1..=12).contains(&month) || day == 0 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    let secs = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Convert Windows FILETIME (100-nanosecond intervals since 1601-01-01).
+pub fn from_filetime(filetime: u64) -> Option<SystemTime> {
+    if filetime == 0 {
+        return None;
+    }
+    let secs = filetime / 10_000_000;
+    let nanos = (filetime % 10_000_000) as u32 * 100;
+    let since_epoch = Duration::new(secs.checked_sub(FILETIME_UNIX_EPOCH_DIFF_SECS)?, nanos);
+    Some(UNIX_EPOCH + since_epoch)
+}
+
+/// Days since 1970-01-01 from a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Proleptic Gregorian date from days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_timestamp, from_dos_date_time, from_filetime};
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn format_unix_epoch() {
+        assert_eq!("1970-01-01 00:00:00 UTC", format_timestamp(&UNIX_EPOCH));
+    }
+
+    #[test]
+    fn convert_dos_date_time() {
+        // 2024-03-11 22:32:10
+        let date = ((2024 - 1980) << 9) | (3 << 5) | 11;
+        let time = (22 << 11) | (32 << 5) | 5;
+        let ts = from_dos_date_time(date, time).unwrap();
+        assert_eq!("2024-03-11 22:32:10 UTC", format_timestamp(&ts));
+        assert_eq!(None, from_dos_date_time(0, 0));
+    }
+
+    #[test]
+    fn convert_filetime() {
+        assert_eq!(Some(UNIX_EPOCH), from_filetime(116_444_736_000_000_000));
+        assert_eq!(None, from_filetime(0));
+    }
+}
@@ -0,0 +1,36 @@
+//! Build metadata embedded by `build.rs`.
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const RUSTC_VERSION: &str = env!("RMEXT_RUSTC_VERSION");
+pub const TARGET: &str = env!("RMEXT_TARGET");
+pub const BUILD_PROFILE: &str = env!("RMEXT_BUILD_PROFILE");
+/// `git describe` of the source tree. `None` unless built from a git checkout.
+pub const GIT_DESCRIBE: Option<&str> = option_env!("RMEXT_GIT_DESCRIBE");
+
+/// Format the build metadata as lines of `key: value`.
+pub fn format_version_info() -> String {
+    let mut lines = vec![
+        format!("rmext {}", VERSION),
+        format!("rustc: {}", RUSTC_VERSION),
+        format!("target: {}", TARGET),
+        format!("build: {}", BUILD_PROFILE),
+    ];
+    if let Some(git_describe) = GIT_DESCRIBE {
+        lines.push(format!("commit: {}", git_describe));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_version_info;
+
+    #[test]
+    fn format_build_metadata() {
+        let version_info = format_version_info();
+        let lines = version_info.lines().collect::<Vec<_>>();
+        assert_eq!(format!("rmext {}", env!("CARGO_PKG_VERSION")), lines[0]);
+        assert!(lines[1].starts_with("rustc: 1."));
+        assert!(lines[3].starts_with("build: "));
+    }
+}